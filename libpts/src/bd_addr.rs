@@ -28,7 +28,7 @@ impl BdAddr {
 }
 
 impl BdAddr {
-    pub fn new(value: [u8; 6]) -> Self {
+    pub const fn new(value: [u8; 6]) -> Self {
         Self(value)
     }
 }
@@ -0,0 +1,281 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal in-process controller, answering the commands sent
+//! by the PTS when its stack is initialized. It cannot connect,
+//! but it lets the PTS start without a real controller.
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use crate::bd_addr::BdAddr;
+use crate::h4::{PacketType, Reassembler};
+
+const SUCCESS: u8 = 0x00;
+
+const COMMAND_COMPLETE: u8 = 0x0e;
+
+const LOCAL_NAME: &[u8] = b"pts-bot fake controller";
+
+/// Bluetooth 5.3.
+const HCI_VERSION: u8 = 0x0c;
+
+/// LMP features page 0 of a dual mode controller,
+/// with Secure Simple Pairing and extended features.
+const LMP_FEATURES: [u8; 8] = [0xff, 0xfe, 0x8f, 0xfe, 0xd8, 0x3f, 0x5b, 0x87];
+
+/// LE features up to the extended scanner filter policies.
+const LE_FEATURES: [u8; 8] = [0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+/// Controller answering the HCI commands written to it with the
+/// events read from it, both H4 framed. Every command without
+/// a dedicated answer completes successfully without parameters.
+pub struct Controller {
+    address: BdAddr,
+    reassembler: Reassembler,
+    output: VecDeque<u8>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl Controller {
+    pub fn new(address: BdAddr) -> Self {
+        Controller {
+            address,
+            reassembler: Reassembler::default(),
+            output: VecDeque::new(),
+            waker: None,
+            closed: false,
+        }
+    }
+
+    // Return parameters of the Command Complete of the command.
+    fn return_parameters(&self, opcode: u16, parameters: &[u8]) -> Vec<u8> {
+        let mut result = vec![SUCCESS];
+        match opcode {
+            // Read Local Name
+            0x0c14 => {
+                let mut name = [0u8; 248];
+                name[..LOCAL_NAME.len()].copy_from_slice(LOCAL_NAME);
+                result.extend(name);
+            }
+            // Read Class of Device
+            0x0c23 => result.extend([0x00, 0x00, 0x00]),
+            // Read Voice Setting
+            0x0c25 => result.extend(0x0060u16.to_le_bytes()),
+            // Read Stored Link Key: maximum and read keys
+            0x0c0d => result.extend([0x00, 0x00, 0x00, 0x00]),
+            // Delete Stored Link Key: deleted keys
+            0x0c12 => result.extend([0x00, 0x00]),
+            // Read Local Version Information
+            0x1001 => {
+                result.push(HCI_VERSION);
+                result.extend(0u16.to_le_bytes());
+                result.push(HCI_VERSION);
+                // Manufacturer: not assigned
+                result.extend(0xffffu16.to_le_bytes());
+                result.extend(0u16.to_le_bytes());
+            }
+            // Read Local Supported Commands: all of them
+            0x1002 => result.extend([0xff; 64]),
+            // Read Local Supported Features
+            0x1003 => result.extend(LMP_FEATURES),
+            // Read Local Extended Features
+            0x1004 => {
+                let page = parameters.first().copied().unwrap_or(0);
+                result.extend([page, 0x01]);
+                result.extend(match page {
+                    0 => LMP_FEATURES,
+                    _ => [0; 8],
+                });
+            }
+            // Read Buffer Size: ACL and SCO lengths and counts
+            0x1005 => {
+                result.extend(1021u16.to_le_bytes());
+                result.push(64);
+                result.extend(8u16.to_le_bytes());
+                result.extend(8u16.to_le_bytes());
+            }
+            // Read BD_ADDR
+            0x1009 => result.extend(self.address.iter().rev()),
+            // Read Local Supported Codecs: none
+            0x100b => result.extend([0x00, 0x00]),
+            // LE Read Buffer Size
+            0x2002 => {
+                result.extend(251u16.to_le_bytes());
+                result.push(8);
+            }
+            // LE Read Local Supported Features
+            0x2003 => result.extend(LE_FEATURES),
+            // LE Read Filter Accept List Size
+            0x200f => result.push(16),
+            // LE Rand
+            0x2018 => result.extend([0x5a; 8]),
+            // LE Read Supported States: all of them
+            0x201c => result.extend([0xff; 8]),
+            // LE Read Suggested Default Data Length
+            0x2023 => {
+                result.extend(251u16.to_le_bytes());
+                result.extend(2120u16.to_le_bytes());
+            }
+            // LE Read Resolving List Size
+            0x202a => result.push(16),
+            // LE Read Maximum Data Length
+            0x202f => {
+                for value in [251u16, 2120, 251, 2120] {
+                    result.extend(value.to_le_bytes());
+                }
+            }
+            // LE Read Number of Supported Advertising Sets
+            0x203b => result.push(1),
+            _ => {}
+        }
+        result
+    }
+
+    fn command(&mut self, packet: &[u8]) {
+        let opcode = u16::from_le_bytes([packet[1], packet[2]]);
+        let return_parameters = self.return_parameters(opcode, &packet[4..]);
+
+        // One more command can be sent.
+        let mut parameters = vec![0x01];
+        parameters.extend(opcode.to_le_bytes());
+        parameters.extend(return_parameters);
+
+        self.output.extend([
+            PacketType::Event as u8,
+            COMMAND_COMPLETE,
+            parameters.len() as u8,
+        ]);
+        self.output.extend(parameters);
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncWrite for Controller {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // The data sent by the host is dropped, there is no connection.
+        for packet in self.reassembler.push(buf) {
+            if packet[0] == PacketType::Command as u8 {
+                self.command(&packet);
+            }
+        }
+        self.wake();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.closed = true;
+        self.wake();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for Controller {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.output.is_empty() {
+            if self.closed {
+                return Poll::Ready(Ok(0));
+            }
+            self.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let read = buf.len().min(self.output.len());
+        for (byte, output) in buf.iter_mut().zip(self.output.drain(..read)) {
+            *byte = output;
+        }
+        Poll::Ready(Ok(read))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Controller;
+    use crate::bd_addr::BdAddr;
+    use async_io::block_on;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_initialization() {
+        let mut controller = Controller::new(BdAddr::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]));
+        block_on(async {
+            // Reset, Read BD_ADDR, and Set Event Mask sent in two parts
+            controller
+                .write_all(&[0x01, 0x03, 0x0c, 0x00, 0x01, 0x09, 0x10, 0x00, 0x01])
+                .await
+                .unwrap();
+            controller
+                .write_all(&[
+                    0x01, 0x0c, 0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3f,
+                ])
+                .await
+                .unwrap();
+            controller.close().await.unwrap();
+
+            let mut output = Vec::new();
+            controller.read_to_end(&mut output).await.unwrap();
+            assert_eq!(
+                output,
+                [
+                    &[0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00][..],
+                    &[0x04, 0x0e, 0x0a, 0x01, 0x09, 0x10, 0x00],
+                    &[0x66, 0x55, 0x44, 0x33, 0x22, 0x11],
+                    &[0x04, 0x0e, 0x04, 0x01, 0x01, 0x0c, 0x00],
+                ]
+                .concat()
+            );
+        });
+    }
+
+    #[test]
+    fn test_read_local_version() {
+        let mut controller = Controller::new(BdAddr::NULL);
+        block_on(async {
+            controller
+                .write_all(&[0x01, 0x01, 0x10, 0x00])
+                .await
+                .unwrap();
+            let mut output = [0u8; 15];
+            controller.read_exact(&mut output).await.unwrap();
+            assert_eq!(
+                output,
+                [
+                    0x04, 0x0e, 0x0c, 0x01, 0x01, 0x10, 0x00, 0x0c, 0x00, 0x00, 0x0c, 0xff, 0xff,
+                    0x00, 0x00
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! H4 framing of the HCI packets: each packet is preceded by
+//! a byte giving its type.
+
+use serde::Serialize;
+
+/// Direction of a packet on the HCI link of the PTS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Direction {
+    /// From the PTS to the controller.
+    Sent,
+    /// From the controller to the PTS.
+    Received,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Command = 0x01,
+    Acl = 0x02,
+    Sco = 0x03,
+    Event = 0x04,
+    Iso = 0x05,
+}
+
+impl PacketType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x01 => PacketType::Command,
+            0x02 => PacketType::Acl,
+            0x03 => PacketType::Sco,
+            0x04 => PacketType::Event,
+            0x05 => PacketType::Iso,
+            _ => return None,
+        })
+    }
+
+    /// Length of the HCI header, after the H4 type.
+    pub fn header_length(self) -> usize {
+        match self {
+            PacketType::Command | PacketType::Sco => 3,
+            PacketType::Acl | PacketType::Iso => 4,
+            PacketType::Event => 2,
+        }
+    }
+
+    /// Length of the parameters or data following `header`.
+    fn payload_length(self, header: &[u8]) -> usize {
+        match self {
+            PacketType::Command | PacketType::Sco => header[2] as usize,
+            PacketType::Acl => u16::from_le_bytes([header[2], header[3]]) as usize,
+            PacketType::Iso => (u16::from_le_bytes([header[2], header[3]]) & 0x3fff) as usize,
+            PacketType::Event => header[1] as usize,
+        }
+    }
+}
+
+/// Split a stream of H4 bytes, as read from the transport, into
+/// complete packets. The bytes which do not start a known packet
+/// type are dropped until the stream is in sync again.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    buffer: Vec<u8>,
+}
+
+impl Reassembler {
+    /// Add `data` to the stream, returning the packets it completes,
+    /// each starting with its H4 type.
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(data);
+
+        let mut packets = Vec::new();
+        while let Some(&first) = self.buffer.first() {
+            let packet_type = match PacketType::from_u8(first) {
+                Some(packet_type) => packet_type,
+                None => {
+                    self.buffer.remove(0);
+                    continue;
+                }
+            };
+            let header_length = 1 + packet_type.header_length();
+            if self.buffer.len() < header_length {
+                break;
+            }
+            let length = header_length + packet_type.payload_length(&self.buffer[1..header_length]);
+            if self.buffer.len() < length {
+                break;
+            }
+            packets.push(self.buffer.drain(..length).collect());
+        }
+        packets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Reassembler;
+
+    #[test]
+    fn test_reassemble() {
+        let mut reassembler = Reassembler::default();
+        let reset = [0x01, 0x03, 0x0c, 0x00];
+        let command_complete = [0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00];
+        let acl = [0x02, 0x40, 0x20, 0x05, 0x00, 0x01, 0x00, 0x01, 0x00, 0xaa];
+
+        assert_eq!(reassembler.push(&reset), [reset.to_vec()]);
+        assert!(reassembler.push(&command_complete[..2]).is_empty());
+        assert!(reassembler.push(&command_complete[2..5]).is_empty());
+        assert_eq!(
+            reassembler.push(&[&command_complete[5..], &acl[..]].concat()),
+            [command_complete.to_vec(), acl.to_vec()]
+        );
+
+        // Unknown types are skipped
+        assert_eq!(
+            reassembler.push(&[&[0xff, 0x00][..], &reset[..]].concat()),
+            [reset.to_vec()]
+        );
+    }
+}
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -22,13 +22,37 @@ use nix::pty;
 
 use async_io::Async;
 
+use async_channel::Sender;
 use futures_lite::{ready, AsyncRead, AsyncWrite};
 
+use crate::h4::{Direction, Reassembler};
 use crate::wine::Wine;
 
 pub struct HCIPort {
     pty: Async<pty::PtyMaster>,
+    path: PathBuf,
     waiting_read: bool,
+    tap: Option<Tap>,
+}
+
+/// Reassembly of the packets going through the port, in both directions.
+struct Tap {
+    packets: Sender<(Direction, Vec<u8>)>,
+    sent: Reassembler,
+    received: Reassembler,
+}
+
+impl Tap {
+    fn push(&mut self, direction: Direction, data: &[u8]) {
+        let reassembler = match direction {
+            Direction::Sent => &mut self.sent,
+            Direction::Received => &mut self.received,
+        };
+        for packet in reassembler.push(data) {
+            // The packets are dropped once nobody listens
+            let _ = self.packets.try_send((direction, packet));
+        }
+    }
 }
 
 pub struct WineHCIPort<'wine> {
@@ -36,27 +60,48 @@ pub struct WineHCIPort<'wine> {
     pub(crate) com: Option<String>,
 }
 
-impl<'a> HCIPort {
-    pub fn bind(wine: &'a Wine) -> io::Result<(HCIPort, WineHCIPort<'a>)> {
+impl<'wine> WineHCIPort<'wine> {
+    /// Bind the HCI port at `path` to a COM port of Wine.
+    pub fn bind(wine: &'wine Wine, path: &Path) -> io::Result<Self> {
+        let com = wine.bind_com_port(path)?;
+        Ok(WineHCIPort {
+            wine,
+            com: Some(com),
+        })
+    }
+}
+
+impl HCIPort {
+    /// Open the pseudo terminal the PTS connects to.
+    pub fn open() -> io::Result<HCIPort> {
         let pty = pty::posix_openpt(OFlag::O_RDWR | OFlag::O_NOCTTY)?;
 
         pty::grantpt(&pty)?;
         pty::unlockpt(&pty)?;
 
-        let path = pty::ptsname_r(&pty)?;
+        let path = PathBuf::from(pty::ptsname_r(&pty)?);
 
-        let com = wine.bind_com_port(Path::new(&path))?;
+        Ok(HCIPort {
+            pty: Async::new(pty)?,
+            path,
+            waiting_read: true,
+            tap: None,
+        })
+    }
 
-        Ok((
-            HCIPort {
-                pty: Async::new(pty)?,
-                waiting_read: true,
-            },
-            WineHCIPort {
-                com: Some(com),
-                wine,
-            },
-        ))
+    /// Path of the other side of the pseudo terminal.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Send a copy of each packet read from or written to the port,
+    /// the PTS being on the other side.
+    pub(crate) fn tap(&mut self, packets: Sender<(Direction, Vec<u8>)>) {
+        self.tap = Some(Tap {
+            packets,
+            sent: Reassembler::default(),
+            received: Reassembler::default(),
+        });
     }
 }
 
@@ -71,6 +116,9 @@ impl AsyncRead for HCIPort {
                 Ok(read) => {
                     // Read was successful, something is connected
                     self.waiting_read = false;
+                    if let Some(tap) = &mut self.tap {
+                        tap.push(Direction::Sent, &buf[..read]);
+                    }
                     return Poll::Ready(Ok(read));
                 }
                 Err(err) if err.kind() == io::Error::from(nix::errno::Errno::EIO).kind() => {
@@ -95,7 +143,11 @@ impl AsyncWrite for HCIPort {
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
         let pty: &mut Async<pty::PtyMaster> = &mut self.pty;
-        Pin::new(pty).poll_write(cx, buf)
+        let written = ready!(Pin::new(pty).poll_write(cx, buf))?;
+        if let Some(tap) = &mut self.tap {
+            tap.push(Direction::Received, &buf[..written]);
+        }
+        Poll::Ready(Ok(written))
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
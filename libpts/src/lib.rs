@@ -14,11 +14,16 @@
 
 mod at;
 mod bd_addr;
+pub mod controller;
+pub mod h4;
 mod hci;
 mod installer;
 mod log;
 pub mod logger;
+mod mapping;
 mod mmi;
+mod mock;
+pub mod packet;
 mod pts;
 mod ttcn;
 mod wine;
@@ -28,6 +33,7 @@ use std::collections::HashMap;
 use std::convert::identity;
 use std::io;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::task::Poll;
 use std::time::Duration;
 
@@ -42,7 +48,10 @@ use crate::wine::{Wine, WineArch};
 use crate::xml_model::{ets::Ets, picsx::Pics, pixitx::Pixit, XMLModel};
 
 pub use crate::log::{final_verdict, map_with_stack, Event, EventKind};
+pub use crate::mapping::{Expression, MappingError};
+pub use crate::mock::Mock;
 pub use crate::pts::MMIStyle;
+pub use crate::xml_model::{picsx::Row as PicsRow, pixitx::Row as PixitRow};
 
 pub struct Interaction {
     pts_addr: BdAddr,
@@ -52,10 +61,49 @@ pub struct Interaction {
 
 pub type HCI = HCIPort;
 
+/// Origin of the value of an ICS item.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IcsSource {
+    /// Set with `PTS::set_ics`.
+    Config,
+    /// Default value of the PICSX file.
+    Default,
+}
+
+/// Test case defined in the ETS of a profile.
+#[derive(Debug)]
+pub struct EtsTestCase<'a> {
+    pub name: &'a str,
+    /// Names of the ETS groups containing the test, outermost first.
+    pub groups: Vec<&'a str>,
+    pub description: &'a str,
+    /// ICS expression which enables the test.
+    pub mapping: &'a str,
+    pub enabled: bool,
+}
+
+/// Messages printed by the PTS server.
+type Messages<'a> = Pin<Box<dyn Stream<Item = io::Result<Message>> + 'a>>;
+
+/// Sender of the answers to the PTS server.
+type SendAnswer<'a> = Box<dyn FnMut(&str) + 'a>;
+
+/// Runner of the PTS server.
+enum Backend {
+    /// `server.exe` in a Wine prefix with the PTS installed.
+    Wine(Wine),
+    /// Scripts replayed in process, for tests.
+    Mock(Mock),
+}
+
 pub struct PTS {
-    wine: Wine,
+    backend: Backend,
     ics: HashMap<String, bool>,
     ixit: HashMap<String, String>,
+    profile_ics: HashMap<String, HashMap<String, bool>>,
+    profile_ixit: HashMap<String, HashMap<String, String>>,
+    test_ics: HashMap<String, HashMap<String, bool>>,
+    test_ixit: HashMap<String, HashMap<String, String>>,
 }
 
 pub struct Profile<'pts> {
@@ -72,6 +120,8 @@ pub enum InstallError {
     Wine(#[source] wine::Error),
     #[error("Server install failed ({0})")]
     Server(#[source] io::Error),
+    #[error("Prefix copy failed ({0})")]
+    Copy(#[source] io::Error),
 }
 
 #[derive(Debug, Error)]
@@ -114,23 +164,121 @@ impl PTS {
         installer::install_server(&wine).map_err(InstallError::Server)?;
 
         Ok(Self {
-            wine,
+            backend: Backend::Wine(wine),
             ics: HashMap::new(),
             ixit: HashMap::new(),
+            profile_ics: HashMap::new(),
+            profile_ixit: HashMap::new(),
+            test_ics: HashMap::new(),
+            test_ixit: HashMap::new(),
         })
     }
 
+    /// Create an independent PTS environment in `directory`, running
+    /// its own Wine server on a copy of this installation prefix.
+    /// Any previous content of `directory` is replaced.
+    pub fn clone_to(&self, directory: PathBuf) -> Result<Self, InstallError> {
+        let backend = match &self.backend {
+            Backend::Wine(wine) => {
+                wine.copy_prefix(&directory).map_err(InstallError::Copy)?;
+                Backend::Wine(Wine::spawn(directory, WineArch::Win32).map_err(InstallError::Wine)?)
+            }
+            Backend::Mock(mock) => Backend::Mock(mock.clone()),
+        };
+
+        Ok(Self {
+            backend,
+            ics: self.ics.clone(),
+            ixit: self.ixit.clone(),
+            profile_ics: self.profile_ics.clone(),
+            profile_ixit: self.profile_ixit.clone(),
+            test_ics: self.test_ics.clone(),
+            test_ixit: self.test_ixit.clone(),
+        })
+    }
+
+    /// PTS replaying the scripts of `mock` instead of running
+    /// the PTS server, to test without Wine nor PTS install.
+    pub fn mock(mock: Mock) -> Self {
+        Self {
+            backend: Backend::Mock(mock),
+            ics: HashMap::new(),
+            ixit: HashMap::new(),
+            profile_ics: HashMap::new(),
+            profile_ixit: HashMap::new(),
+            test_ics: HashMap::new(),
+            test_ixit: HashMap::new(),
+        }
+    }
+
     pub fn set_ics(&mut self, name: &str, value: bool) {
         self.ics.insert(name.to_owned(), value);
     }
     pub fn set_ixit(&mut self, name: &str, value: &str) {
         self.ixit.insert(name.to_owned(), value.to_owned());
     }
+    /// Set an ICS value for one profile only, it takes
+    /// precedence over the value set with `set_ics`.
+    pub fn set_profile_ics(&mut self, profile: &str, name: &str, value: bool) {
+        self.profile_ics
+            .entry(profile.to_owned())
+            .or_default()
+            .insert(name.to_owned(), value);
+    }
+    /// Set an IXIT value for one profile only, it takes
+    /// precedence over the value set with `set_ixit`.
+    pub fn set_profile_ixit(&mut self, profile: &str, name: &str, value: &str) {
+        self.profile_ixit
+            .entry(profile.to_owned())
+            .or_default()
+            .insert(name.to_owned(), value.to_owned());
+    }
+    /// Set an ICS value for the run of one test only. Unlike `set_ics`,
+    /// it does not change which tests are enabled.
+    pub fn set_test_ics(&mut self, test: &str, name: &str, value: bool) {
+        self.test_ics
+            .entry(test.to_owned())
+            .or_default()
+            .insert(name.to_owned(), value);
+    }
+    /// Set an IXIT value for the run of one test only, it takes
+    /// precedence over the profile and global values.
+    pub fn set_test_ixit(&mut self, test: &str, name: &str, value: &str) {
+        self.test_ixit
+            .entry(test.to_owned())
+            .or_default()
+            .insert(name.to_owned(), value.to_owned());
+    }
+
+    /// Names of the profiles which have an ETS installed.
+    pub fn profiles(&self) -> io::Result<Vec<String>> {
+        let wine = match &self.backend {
+            Backend::Wine(wine) => wine,
+            Backend::Mock(mock) => return Ok(mock.profiles()),
+        };
+        let directory = wine.drive_c().join(installer::PTS_PATH).join(Ets::PATH);
+        let mut profiles = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "xml") {
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    profiles.push(name.to_owned());
+                }
+            }
+        }
+        profiles.sort();
+        Ok(profiles)
+    }
 
     pub fn profile(&self, name: &str) -> Result<Profile<'_>, xml_model::Error> {
-        let ets = Ets::parse(name, &self.wine)?;
-        let pics = Pics::parse(name, &self.wine)?;
-        let pixit = Pixit::parse(name, &self.wine)?;
+        let (ets, pics, pixit) = match &self.backend {
+            Backend::Wine(wine) => (
+                Ets::parse(name, wine)?,
+                Pics::parse(name, wine)?,
+                Pixit::parse(name, wine)?,
+            ),
+            Backend::Mock(mock) => mock.profile(name)?,
+        };
 
         Ok(Profile {
             pts: self,
@@ -143,23 +291,94 @@ impl PTS {
 }
 
 impl<'pts> Profile<'pts> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Names of the ETS groups containing the test, outermost first.
+    pub fn test_groups(&self, test: &str) -> Option<Vec<&str>> {
+        self.ets.groups_of(test)
+    }
+
+    /// ICS definitions of the profile, with their default values.
+    pub fn pics(&self) -> impl Iterator<Item = &PicsRow> {
+        self.pics.iter()
+    }
+
+    /// IXIT definitions of the profile, with their default values.
+    pub fn pixit(&self) -> impl Iterator<Item = &PixitRow> {
+        self.pixit.iter()
+    }
+
+    /// Value of an ICS item used to enable the tests and its origin,
+    /// `None` when the item is not defined.
+    pub fn ics(&self, name: &str) -> Option<(bool, IcsSource)> {
+        if let Some(value) = self.config_ics(name) {
+            return Some((*value, IcsSource::Config));
+        }
+        self.ics_default(name)
+            .map(|value| (value, IcsSource::Default))
+    }
+
+    // ICS value set for the profile or for all of them.
+    fn config_ics(&self, name: &str) -> Option<&bool> {
+        self.pts
+            .profile_ics
+            .get(&self.name)
+            .and_then(|ics| ics.get(name))
+            .or_else(|| self.pts.ics.get(name))
+    }
+
+    /// Default value of an ICS item in the PICSX file.
+    pub fn ics_default(&self, name: &str) -> Option<bool> {
+        self.pics
+            .iter()
+            .find(|row| row.name == name)
+            .map(|row| row.value)
+    }
+
+    fn ics_value(&self, name: &str) -> Option<bool> {
+        self.ics(name).map(|(value, _)| value)
+    }
+
     pub fn tests(&self) -> impl Iterator<Item = String> + '_ {
+        self.ets.enabled_testcases(move |name| self.ics_value(name))
+    }
+
+    /// Tests enabled by the ICS values of `ics` instead of the ones
+    /// set with `PTS::set_ics`, the other items keep their default.
+    pub fn tests_with_ics<'a>(
+        &'a self,
+        ics: &'a HashMap<String, bool>,
+    ) -> impl Iterator<Item = String> + 'a {
         self.ets.enabled_testcases(move |name| {
-            self.pts.ics.get(name).copied().or_else(|| {
-                self.pics
-                    .iter()
-                    .find(|row| row.name == name)
-                    .map(|row| row.value)
-            })
+            ics.get(name).copied().or_else(|| self.ics_default(name))
         })
     }
 
+    /// All the test cases of the ETS, enabled or not, in ETS order.
+    pub fn test_cases(&self) -> Vec<EtsTestCase<'_>> {
+        self.ets
+            .testcases_with_groups()
+            .into_iter()
+            .map(|(groups, testcase)| EtsTestCase {
+                name: &testcase.name,
+                groups,
+                description: &testcase.description,
+                mapping: &testcase.mapping,
+                enabled: testcase.is_enabled(&|name: &str| self.ics_value(name)),
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn run_test<Fut1, Err1, Fut2, Err2>(
         &self,
         test: &str,
         iut_addr: BdAddr,
         mut pipe_hci: impl FnMut(HCI) -> Fut1 + 'pts,
         mut interact: impl FnMut(Interaction) -> Fut2 + 'pts,
+        raw_output: impl FnMut(&str) + 'pts,
         audio_output_path: Option<&str>,
         inactivity_timeout: u64,
     ) -> impl Stream<Item = Result<Event, RunError<Err1, Err2>>> + 'pts
@@ -169,7 +388,10 @@ impl<'pts> Profile<'pts> {
         Fut2: 'pts + Future<Output = Result<String, Err2>>,
         Err2: 'pts,
     {
-        let (port, wineport) = HCIPort::bind(&self.pts.wine).expect("HCI port");
+        let mut port = HCIPort::open().expect("HCI port");
+        let hci_path = port.path().to_owned();
+        let (packets_tx, mut packets) = async_channel::unbounded();
+        port.tap(packets_tx);
 
         let hci = Box::pin(async move {
             pipe_hci(port).await.map_err(RunError::Pipe)?;
@@ -178,8 +400,14 @@ impl<'pts> Profile<'pts> {
 
         let octet_addr = format!("{:#}", iut_addr);
 
+        let test_ics = self.pts.test_ics.get(test);
+        let test_ixit = self.pts.test_ixit.get(test);
+
         let pics = self.pics.iter().map(|row| {
-            let value = self.pts.ics.get(&row.name).unwrap_or(&row.value);
+            let value = test_ics
+                .and_then(|ics| ics.get(&row.name))
+                .or_else(|| self.config_ics(&row.name))
+                .unwrap_or(&row.value);
             let value = if *value { "TRUE" } else { "FALSE" };
             (&*row.name, "BOOLEAN", value)
         });
@@ -188,16 +416,41 @@ impl<'pts> Profile<'pts> {
             "TSPX_bd_addr_iut" => ("TSPX_bd_addr_iut", "OCTETSTRING", &*octet_addr),
             "TSPX_delete_link_key" => ("TSPX_delete_link_key", "BOOLEAN", "TRUE"),
             _ => {
-                let value = self.pts.ixit.get(&row.name).unwrap_or(&row.value);
+                let value = test_ixit
+                    .and_then(|ixit| ixit.get(&row.name))
+                    .or_else(|| {
+                        self.pts
+                            .profile_ixit
+                            .get(&self.name)
+                            .and_then(|ixit| ixit.get(&row.name))
+                    })
+                    .or_else(|| self.pts.ixit.get(&row.name))
+                    .unwrap_or(&row.value);
                 (&*row.name, &*row.value_type[0], &**value)
             }
         });
 
         let parameters = pics.chain(pixit);
 
-        let (messages, mut send_answer) =
-            pts::Server::spawn(wineport, &self.name, test, parameters, audio_output_path)
-                .into_parts();
+        let (messages, mut send_answer): (Messages<'pts>, SendAnswer<'pts>) =
+            match &self.pts.backend {
+                Backend::Wine(wine) => {
+                    let (messages, send_answer) = pts::Server::spawn(
+                        wine,
+                        &hci_path,
+                        &self.name,
+                        test,
+                        parameters,
+                        audio_output_path,
+                    )
+                    .into_parts(raw_output);
+                    (messages.boxed_local(), Box::new(send_answer))
+                }
+                Backend::Mock(mock) => {
+                    let (messages, send_answer) = mock.spawn(test, raw_output);
+                    (messages.boxed_local(), Box::new(send_answer))
+                }
+            };
 
         let mut messages = messages
             .map(|r| r.map_err(RunError::IO))
@@ -276,12 +529,22 @@ impl<'pts> Profile<'pts> {
             .then(identity)
             .filter_map(Result::transpose));
 
-        log::parse(messages)
+        // The packets exchanged before the PTS address is known
+        // are queued, they come before the first log event.
+        let mut events = Box::pin(log::parse(messages));
+        stream::poll_fn(move |cx| {
+            if let Poll::Ready(Some((direction, packet))) = packets.poll_next(cx) {
+                return Poll::Ready(Some(Ok(log::hci_event(direction, &packet))));
+            }
+            events.poll_next(cx)
+        })
     }
 
     /// Delete the PTS link key file.
     /// NB. The location of the file might change from version to version.
     pub fn delete_link_key(&self) {
-        let _ = std::fs::remove_file(self.pts.wine.drive_c().join("pts/bin/link_key.txt"));
+        if let Backend::Wine(wine) = &self.pts.backend {
+            let _ = std::fs::remove_file(wine.drive_c().join("pts/bin/link_key.txt"));
+        }
     }
 }
@@ -12,14 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::h4::Direction;
+use crate::packet::Packet;
 use crate::pts::{LogType, Message};
 use crate::ttcn;
 
 use std::iter::Iterator;
 
 use futures_lite::{pin, Stream, StreamExt};
+use serde::Serialize;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
 pub enum TimerEvent {
     Start,
     Stop,
@@ -28,7 +31,7 @@ pub enum TimerEvent {
     Timeout,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
 pub enum EventKind {
     EnterStep,
     ExitStep,
@@ -42,12 +45,16 @@ pub enum EventKind {
     TestEnd,
     MatchFailed,
     Timer(TimerEvent),
+    /// HCI packet between the PTS and the controller.
+    Hci(Direction),
+    /// Fault injected on the HCI link, reported by the caller.
+    Fault(Direction),
     Error,
     ManMachineInterface,
     Ignored,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Event {
     pub kind: EventKind,
     pub time: Option<u32>,
@@ -293,6 +300,16 @@ fn parse_log_message(logtype: LogType, message: String) -> Event {
     }
 }
 
+pub(crate) fn hci_event(direction: Direction, packet: &[u8]) -> Event {
+    Event {
+        kind: EventKind::Hci(direction),
+        time: None,
+        number: None,
+        name: Packet::decode(packet).to_string(),
+        values: None,
+    }
+}
+
 pub fn parse<E>(
     messages: impl Stream<Item = Result<Message, E>>,
 ) -> impl Stream<Item = Result<Event, E>> {
@@ -18,6 +18,7 @@ use std::io::{self, Write};
 
 use termion::{color, style};
 
+use crate::h4::Direction;
 use crate::log::{Event, EventKind};
 
 fn calculate_hash<T: Hash>(t: &T) -> u64 {
@@ -47,6 +48,8 @@ fn kind_name(kind: &EventKind) -> &'static str {
         EventKind::TestEnd => "Test End",
         EventKind::MatchFailed => "Match",
         EventKind::Timer(_) => "Timer",
+        EventKind::Hci(_) => "HCI",
+        EventKind::Fault(_) => "Fault",
         EventKind::Error => "Error",
         EventKind::ManMachineInterface => "MMI",
         EventKind::Ignored => "Ignored",
@@ -67,6 +70,8 @@ fn kind_color(kind: &EventKind) -> (&'static dyn color::Color, &'static dyn colo
         EventKind::TestEnd => (&color::LightBlack, &color::LightWhite),
         EventKind::MatchFailed => (&color::LightWhite, &color::Yellow),
         EventKind::Timer(_) => (&color::Cyan, &color::LightWhite),
+        EventKind::Hci(_) => (&color::LightBlack, &color::Blue),
+        EventKind::Fault(_) => (&color::LightWhite, &color::LightRed),
         EventKind::Error => (&color::LightWhite, &color::Red),
         EventKind::ManMachineInterface => (&color::LightWhite, &color::Yellow),
         EventKind::Ignored => (&color::LightBlack, &color::LightWhite),
@@ -132,6 +137,14 @@ pub fn print(to: &mut impl Write, event: &Event, stack: &[String]) -> io::Result
         write!(to, "{:?} ", event)?;
     }
 
+    match event.kind {
+        EventKind::Hci(Direction::Sent) | EventKind::Fault(Direction::Sent) => write!(to, "> ")?,
+        EventKind::Hci(Direction::Received) | EventKind::Fault(Direction::Received) => {
+            write!(to, "< ")?
+        }
+        _ => {}
+    }
+
     print_multiline(to, &event.kind, &event.name)?;
 
     match event.kind {
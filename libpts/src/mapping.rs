@@ -0,0 +1,420 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parser of the `Mapping` of the ETS test cases:
+//!
+//! ```text
+//! expression = and { OR and }
+//! and        = not { AND not }
+//! not        = ( NOT | "!" | "~" ) not | primary
+//! primary    = "(" expression ")" | TRUE | FALSE | item
+//! ```
+//!
+//! Keywords are case insensitive whole words, `&&`, `&`, `||` and `|`
+//! are accepted as well.
+
+use thiserror::Error;
+
+/// ICS expression enabling a test case, as found
+/// in the `Mapping` of the ETS.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    Not(Box<Expression>),
+    Item(String),
+    Const(bool),
+}
+
+#[derive(Debug, PartialEq, Error)]
+#[error("Invalid mapping at column {column} ({message})")]
+pub struct MappingError {
+    /// Column of the error, starting at 1.
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Const(bool),
+    Item(String),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::And => String::from("AND"),
+            Token::Or => String::from("OR"),
+            Token::Not => String::from("NOT"),
+            Token::Open => String::from("'('"),
+            Token::Close => String::from("')'"),
+            Token::Const(value) => value.to_string().to_uppercase(),
+            Token::Item(name) => format!("'{}'", name),
+            Token::End => String::from("end of mapping"),
+        }
+    }
+}
+
+/// Tokens with their column.
+fn tokenize(mapping: &str) -> Result<Vec<(usize, Token)>, MappingError> {
+    let column = |offset: usize| mapping[..offset].chars().count() + 1;
+    let mut chars = mapping.char_indices().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '!' | '~' => Token::Not,
+            '&' | '|' => {
+                // Single or doubled
+                if chars.peek().map(|(_, next)| *next) == Some(c) {
+                    chars.next();
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut end = offset + c.len_utf8();
+                while let Some((next, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || *c == '_') {
+                        break;
+                    }
+                    end = next + c.len_utf8();
+                    chars.next();
+                }
+                let word = &mapping[offset..end];
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "TRUE" => Token::Const(true),
+                    "FALSE" => Token::Const(false),
+                    _ => Token::Item(word.to_owned()),
+                }
+            }
+            c => {
+                return Err(MappingError {
+                    column: column(offset),
+                    message: format!("unexpected character '{}'", c),
+                })
+            }
+        };
+        tokens.push((column(offset), token));
+    }
+
+    tokens.push((column(mapping.len()), Token::End));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].1
+    }
+
+    fn next(&mut self) -> (usize, Token) {
+        let token = self.tokens[self.position].clone();
+        if token.1 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self, (column, token): (usize, Token)) -> Result<T, MappingError> {
+        Err(MappingError {
+            column,
+            message: format!("unexpected {}", token.describe()),
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expression, MappingError> {
+        let mut operands = vec![self.and()?];
+        while *self.peek() == Token::Or {
+            self.next();
+            operands.push(self.and()?);
+        }
+        Ok(Expression::flatten(operands, false))
+    }
+
+    fn and(&mut self) -> Result<Expression, MappingError> {
+        let mut operands = vec![self.not()?];
+        while *self.peek() == Token::And {
+            self.next();
+            operands.push(self.not()?);
+        }
+        Ok(Expression::flatten(operands, true))
+    }
+
+    fn not(&mut self) -> Result<Expression, MappingError> {
+        match self.next() {
+            (_, Token::Not) => Ok(Expression::Not(Box::new(self.not()?))),
+            (_, Token::Open) => {
+                let expression = self.expression()?;
+                match self.next() {
+                    (_, Token::Close) => Ok(expression),
+                    token => self.unexpected(token),
+                }
+            }
+            (_, Token::Const(value)) => Ok(Expression::Const(value)),
+            (_, Token::Item(name)) => Ok(Expression::Item(name)),
+            token => self.unexpected(token),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(mapping: &str) -> Result<Self, MappingError> {
+        let mut parser = Parser {
+            tokens: tokenize(mapping)?,
+            position: 0,
+        };
+        let expression = parser.expression()?;
+        match parser.next() {
+            (_, Token::End) => Ok(expression),
+            token => parser.unexpected(token),
+        }
+    }
+
+    // Merge the operands of chained binary operators,
+    // A AND (B AND C) is A AND B AND C.
+    fn flatten(operands: Vec<Expression>, and: bool) -> Expression {
+        if operands.len() == 1 {
+            return operands.into_iter().next().unwrap();
+        }
+        let mut flat = Vec::new();
+        for operand in operands {
+            match operand {
+                Expression::And(inner) if and => flat.extend(inner),
+                Expression::Or(inner) if !and => flat.extend(inner),
+                operand => flat.push(operand),
+            }
+        }
+        if and {
+            Expression::And(flat)
+        } else {
+            Expression::Or(flat)
+        }
+    }
+
+    /// Evaluate the expression, `None` when its value depends
+    /// on items which could not be resolved.
+    pub fn evaluate<F: Fn(&str) -> Option<bool>>(&self, get_value: &F) -> Option<bool> {
+        match self {
+            // A single false operand decides an AND, whatever the others
+            Expression::And(operands) => Self::combine(operands, get_value, false),
+            Expression::Or(operands) => Self::combine(operands, get_value, true),
+            Expression::Not(operand) => operand.evaluate(get_value).map(|value| !value),
+            Expression::Item(name) => get_value(name),
+            Expression::Const(value) => Some(*value),
+        }
+    }
+
+    // Value of an AND or an OR: `decisive` when any operand has
+    // that value, the opposite when all of them have the opposite.
+    fn combine<F: Fn(&str) -> Option<bool>>(
+        operands: &[Expression],
+        get_value: &F,
+        decisive: bool,
+    ) -> Option<bool> {
+        let values: Vec<_> = operands
+            .iter()
+            .map(|operand| operand.evaluate(get_value))
+            .collect();
+        if values.contains(&Some(decisive)) {
+            Some(decisive)
+        } else if values.iter().all(Option::is_some) {
+            Some(!decisive)
+        } else {
+            None
+        }
+    }
+
+    /// Names of the ICS items of the expression, in order of appearance.
+    pub fn items(&self) -> Vec<&str> {
+        let mut items = Vec::new();
+        self.collect_items(&mut items);
+        items
+    }
+
+    fn collect_items<'a>(&'a self, items: &mut Vec<&'a str>) {
+        match self {
+            Expression::And(operands) | Expression::Or(operands) => {
+                for operand in operands {
+                    operand.collect_items(items);
+                }
+            }
+            Expression::Not(operand) => operand.collect_items(items),
+            Expression::Item(name) => {
+                if !items.contains(&name.as_str()) {
+                    items.push(name);
+                }
+            }
+            Expression::Const(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Expression::{self, *};
+
+    fn item(name: &str) -> Expression {
+        Item(name.to_owned())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Expression::parse("TSPC_A AND (TSPC_B OR !TSPC_C) AND TSPC_D").unwrap(),
+            And(vec![
+                item("TSPC_A"),
+                Or(vec![item("TSPC_B"), Not(Box::new(item("TSPC_C")))]),
+                item("TSPC_D"),
+            ])
+        );
+        assert_eq!(
+            Expression::parse("TSPC_A && NOT (TSPC_B || ~TSPC_C) or true").unwrap(),
+            Or(vec![
+                And(vec![
+                    item("TSPC_A"),
+                    Not(Box::new(Or(vec![
+                        item("TSPC_B"),
+                        Not(Box::new(item("TSPC_C")))
+                    ])))
+                ]),
+                Const(true),
+            ])
+        );
+        assert_eq!(Expression::parse(" (TSPC_A) ").unwrap(), item("TSPC_A"));
+    }
+
+    #[test]
+    fn parse_keywords_in_items() {
+        // The items are not split on the keywords they contain
+        assert_eq!(
+            Expression::parse("TSPC_SPORT_1 OR TSPC_HANDOVER_2 AND TSPC_NOTIFY_3").unwrap(),
+            Or(vec![
+                item("TSPC_SPORT_1"),
+                And(vec![item("TSPC_HANDOVER_2"), item("TSPC_NOTIFY_3")]),
+            ])
+        );
+        assert_eq!(Expression::parse("ORDER").unwrap(), item("ORDER"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |mapping| {
+            let e = Expression::parse(mapping).unwrap_err();
+            (e.column, e.message)
+        };
+
+        assert_eq!(error(""), (1, String::from("unexpected end of mapping")));
+        assert_eq!(
+            error("TSPC_A AND"),
+            (11, String::from("unexpected end of mapping"))
+        );
+        assert_eq!(
+            error("TSPC_A AND (TSPC_B OR TSPC_C"),
+            (29, String::from("unexpected end of mapping"))
+        );
+        assert_eq!(
+            error("TSPC_A TSPC_B"),
+            (8, String::from("unexpected 'TSPC_B'"))
+        );
+        assert_eq!(
+            error("TSPC_A + 1"),
+            (8, String::from("unexpected character '+'"))
+        );
+        assert_eq!(error("TSPC_A OR )"), (11, String::from("unexpected ')'")));
+        assert_eq!(
+            Expression::parse("é AND").unwrap_err().to_string(),
+            "Invalid mapping at column 1 (unexpected character 'é')"
+        );
+    }
+
+    #[test]
+    fn evaluate() {
+        let expression = Expression::parse("TSPC_A AND (TSPC_B OR TSPC_C)").unwrap();
+        let values = |a, b, c| {
+            move |name: &str| match name {
+                "TSPC_A" => a,
+                "TSPC_B" => b,
+                "TSPC_C" => c,
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            expression.evaluate(&values(Some(true), Some(false), Some(true))),
+            Some(true)
+        );
+        assert_eq!(
+            expression.evaluate(&values(Some(false), None, None)),
+            Some(false)
+        );
+        assert_eq!(
+            expression.evaluate(&values(Some(true), Some(false), None)),
+            None
+        );
+        assert_eq!(expression.items(), ["TSPC_A", "TSPC_B", "TSPC_C"]);
+    }
+
+    // Parse the mappings of every ETS of an installed PTS, found in the
+    // `PTS_ETS_DIR` directory (`drive_c/pts/bin/Bluetooth/Ets` of the
+    // wine prefix), skipped when it is not set.
+    #[test]
+    fn parse_installed_ets() {
+        use crate::xml_model::{ets::Ets, XMLModel};
+
+        let directory = match std::env::var_os("PTS_ETS_DIR") {
+            Some(directory) => directory,
+            None => return,
+        };
+
+        let mut errors = Vec::new();
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("xml".as_ref()) {
+                continue;
+            }
+            let ets = Ets::from_str(&std::fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            for (_, testcase) in ets.testcases_with_groups() {
+                // Test cases without mapping are never enabled
+                if testcase.mapping.trim().is_empty() {
+                    continue;
+                }
+                if let Err(e) = Expression::parse(&testcase.mapping) {
+                    errors.push(format!("{}: {}", testcase.name, e));
+                }
+            }
+        }
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Mock of the PTS server, replaying a script instead of running
+//! `server.exe` in Wine.
+//!
+//! A script is a sequence of the JSON lines printed by the server:
+//!
+//! ```text
+//! {"type": "addr", "value": "001BDC000002"}
+//! {"type": "log", "time": "", "description": "", "message": "...", "logtype": 1}
+//! {"type": "implicit_send", "description": "{1002,A2DP/SRC/AS/BV-01-I,A2DP}...", "style": 69696, "answer": "OK"}
+//! {"type": "sleep", "ms": 1000}
+//! ```
+//!
+//! After an `implicit_send`, the mock waits for the answer like the
+//! server does, and fails when it is not the `answer` of the line.
+//! A `sleep` line pauses the script, the other lines are printed as is.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::time::Duration;
+
+use async_channel::Receiver;
+use async_io::Timer;
+use futures_lite::{stream, Stream};
+use serde_json::Value;
+
+use crate::pts::Message;
+use crate::xml_model::{self, ets::Ets, picsx::Pics, pixitx::Pixit, XMLModel};
+
+#[derive(Debug, Clone)]
+struct MockProfile {
+    ets: String,
+    picsx: String,
+    pixitx: String,
+}
+
+/// Profiles and test scripts of a mock PTS, see `PTS::mock`.
+#[derive(Debug, Clone, Default)]
+pub struct Mock {
+    profiles: HashMap<String, MockProfile>,
+    scripts: HashMap<String, String>,
+}
+
+enum Step {
+    Print {
+        line: String,
+        /// Set for `implicit_send`, with the expected answer if any.
+        answer: Option<Option<String>>,
+    },
+    Sleep(Duration),
+}
+
+impl Step {
+    fn parse(line: &str) -> Self {
+        let value: Value = serde_json::from_str(line).unwrap_or(Value::Null);
+        match value["type"].as_str() {
+            Some("sleep") => Step::Sleep(Duration::from_millis(
+                value["ms"].as_u64().unwrap_or_default(),
+            )),
+            Some("implicit_send") => Step::Print {
+                line: line.to_owned(),
+                answer: Some(value["answer"].as_str().map(str::to_owned)),
+            },
+            _ => Step::Print {
+                line: line.to_owned(),
+                answer: None,
+            },
+        }
+    }
+}
+
+struct Replay<R> {
+    steps: VecDeque<Step>,
+    answers: Receiver<String>,
+    /// Answer the server is waiting for.
+    waiting: Option<Option<String>>,
+    raw: R,
+}
+
+impl Mock {
+    /// Add a profile defined by the content of its ETS, PICSX and PIXITX files.
+    pub fn add_profile(&mut self, name: &str, ets: &str, picsx: &str, pixitx: &str) {
+        self.profiles.insert(
+            name.to_owned(),
+            MockProfile {
+                ets: ets.to_owned(),
+                picsx: picsx.to_owned(),
+                pixitx: pixitx.to_owned(),
+            },
+        );
+    }
+
+    /// Set the script replayed when `test` runs.
+    pub fn add_script(&mut self, test: &str, script: &str) {
+        self.scripts.insert(test.to_owned(), script.to_owned());
+    }
+
+    pub(crate) fn profiles(&self) -> Vec<String> {
+        let mut profiles: Vec<String> = self.profiles.keys().cloned().collect();
+        profiles.sort();
+        profiles
+    }
+
+    pub(crate) fn profile(&self, name: &str) -> Result<(Ets, Pics, Pixit), xml_model::Error> {
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            xml_model::Error::FileNotFound(io::ErrorKind::NotFound.into(), name.to_owned())
+        })?;
+        Ok((
+            Ets::from_str(&profile.ets)?,
+            Pics::from_str(&profile.picsx)?,
+            Pixit::from_str(&profile.pixitx)?,
+        ))
+    }
+
+    /// Replay the script of `test`, like `Server::into_parts`.
+    /// A test without script ends at once.
+    pub(crate) fn spawn<'a>(
+        &self,
+        test: &str,
+        raw: impl FnMut(&str) + 'a,
+    ) -> (
+        impl Stream<Item = io::Result<Message>> + 'a,
+        impl FnMut(&str) + 'a,
+    ) {
+        let steps = self
+            .scripts
+            .get(test)
+            .map(String::as_str)
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(Step::parse)
+            .collect();
+        let (answers_tx, answers) = async_channel::unbounded();
+        let replay = Replay {
+            steps,
+            answers,
+            waiting: None,
+            raw,
+        };
+
+        (
+            stream::unfold(replay, |mut replay| async move {
+                loop {
+                    if let Some(expected) = replay.waiting.take() {
+                        let answer = replay.answers.recv().await.ok()?;
+                        match expected {
+                            Some(expected) if answer != expected => {
+                                let error = io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("Expected answer '{}', got '{}'", expected, answer),
+                                );
+                                return Some((Err(error), replay));
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    match replay.steps.pop_front()? {
+                        Step::Sleep(duration) => {
+                            Timer::after(duration).await;
+                        }
+                        Step::Print { line, answer } => {
+                            replay.waiting = answer;
+                            (replay.raw)(&line);
+                            return Some((Ok(Message::parse(line)), replay));
+                        }
+                    }
+                }
+            }),
+            move |answer: &str| {
+                let _ = answers_tx.try_send(answer.to_owned());
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Mock;
+    use crate::{final_verdict, BdAddr, EventKind, RunError, PTS};
+    use async_io::block_on;
+    use futures_lite::StreamExt;
+    use std::io;
+
+    const ETS: &str = r#"
+        <ETS>
+            <Profile>
+                <Name>A2DP</Name>
+                <Group>
+                    <Name>SRC</Name>
+                    <TestCase>
+                        <Name>A2DP/SRC/AS/BV-01-I</Name>
+                        <Mapping>TSPC_A2DP_1_1</Mapping>
+                    </TestCase>
+                </Group>
+            </Profile>
+        </ETS>"#;
+
+    const PICSX: &str = r#"
+        <PICS>
+            <Rows>
+                <Row>
+                    <Name>TSPC_A2DP_1_1</Name>
+                    <Description>Source</Description>
+                    <Value>TRUE</Value>
+                    <Mandatory>FALSE</Mandatory>
+                </Row>
+            </Rows>
+        </PICS>"#;
+
+    const PIXITX: &str = r#"
+        <PIXIT>
+            <Name>A2DP</Name>
+            <Version></Version>
+            <Rows>
+                <Row>
+                    <Name>TSPX_bd_addr_iut</Name>
+                    <Description>Address of the IUT</Description>
+                    <Type>OCTETSTRING</Type>
+                    <Value>000000000000</Value>
+                </Row>
+            </Rows>
+        </PIXIT>"#;
+
+    const TEST: &str = "A2DP/SRC/AS/BV-01-I";
+
+    const SCRIPT: &str = r#"
+        {"type": "addr", "value": "001BDC000002"}
+        {"type": "log", "time": "", "description": "", "message": "A2DP/SRC/AS/BV-01-I", "logtype": 1}
+        {"type": "log", "time": "+000012 ms", "description": "", "message": ": 1 Enter Test Step A2DP_connect", "logtype": 13}
+        {"type": "implicit_send", "description": "{1002,A2DP/SRC/AS/BV-01-I,A2DP}Connect", "style": 69696, "answer": "OK"}
+        {"type": "log", "time": "", "description": "", "message": ": 1 Exit  Test Step  A2DP_connect", "logtype": 13}
+        {"type": "log", "time": "", "description": "", "message": "VERDICT/PASS", "logtype": 5}
+    "#;
+
+    fn pts(script: &str) -> PTS {
+        let mut mock = Mock::default();
+        mock.add_profile("A2DP", ETS, PICSX, PIXITX);
+        mock.add_script(TEST, script);
+        PTS::mock(mock)
+    }
+
+    type Verdict = Result<Option<String>, RunError<io::Error, io::Error>>;
+
+    // Run the test with an IUT answering `answer` to all the MMI.
+    fn run(pts: &PTS, answer: &'static str, inactivity_timeout: u64) -> (Vec<EventKind>, Verdict) {
+        let profile = pts.profile("A2DP").unwrap();
+        // Closed when the mock drops the raw output at the end of the script
+        let (running, done) = async_channel::bounded::<()>(1);
+        block_on(async {
+            let events = profile
+                .run_test(
+                    TEST,
+                    BdAddr::NULL,
+                    // Like with the server, the HCI pipe lasts until the end of the test
+                    move |port| {
+                        let done = done.clone();
+                        async move {
+                            let _ = done.recv().await;
+                            drop(port);
+                            Ok(())
+                        }
+                    },
+                    move |_| async move { Ok(String::from(answer)) },
+                    move |_| {
+                        let _ = &running;
+                    },
+                    None,
+                    inactivity_timeout,
+                )
+                .await;
+            let mut kinds = Vec::new();
+            let events = events.map(|result| result.inspect(|event| kinds.push(event.kind)));
+            let verdict = final_verdict(events).await;
+            (kinds, verdict)
+        })
+    }
+
+    #[test]
+    fn test_run() {
+        let pts = pts(SCRIPT);
+        assert_eq!(pts.profiles().unwrap(), ["A2DP"]);
+        assert_eq!(
+            pts.profile("A2DP").unwrap().tests().collect::<Vec<_>>(),
+            [TEST]
+        );
+
+        let (kinds, verdict) = run(&pts, "OK", 10);
+        assert_eq!(verdict.unwrap().as_deref(), Some("PASS"));
+        assert_eq!(
+            kinds,
+            [
+                EventKind::TestStart,
+                EventKind::EnterStep,
+                EventKind::ManMachineInterface,
+                EventKind::ExitStep,
+                EventKind::FinalVerdict
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrong_answer() {
+        let (_, verdict) = run(&pts(SCRIPT), "Cancel", 10);
+        match verdict {
+            Err(RunError::IO(e)) => assert_eq!(e.to_string(), "Expected answer 'OK', got 'Cancel'"),
+            verdict => panic!("Unexpected verdict {:?}", verdict),
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        let script = r#"
+            {"type": "addr", "value": "001BDC000002"}
+            {"type": "sleep", "ms": 5000}
+            {"type": "log", "time": "", "description": "", "message": "VERDICT/PASS", "logtype": 5}
+        "#;
+        let (_, verdict) = run(&pts(script), "OK", 1);
+        assert!(matches!(verdict, Err(RunError::Timeout)));
+    }
+
+    #[test]
+    fn test_no_address() {
+        let (_, verdict) = run(&pts(""), "OK", 10);
+        assert!(matches!(verdict, Err(RunError::NoAddress)));
+    }
+}
@@ -0,0 +1,556 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoder of the HCI packets, down to the headers needed to follow
+//! the activity of the controller in the log of a test.
+
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::bd_addr::BdAddr;
+use crate::h4::PacketType;
+
+/// Header of the L2CAP packet starting in an ACL packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct L2cap {
+    pub length: u16,
+    pub cid: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    Command {
+        opcode: u16,
+    },
+    CommandComplete {
+        opcode: u16,
+        /// First return parameter, absent for the NOP opcode.
+        status: Option<u8>,
+    },
+    CommandStatus {
+        opcode: u16,
+        status: u8,
+    },
+    ConnectionComplete {
+        status: u8,
+        handle: u16,
+        address: BdAddr,
+    },
+    DisconnectionComplete {
+        status: u8,
+        handle: u16,
+        reason: u8,
+    },
+    /// LE Connection Complete or LE Enhanced Connection Complete.
+    LeConnectionComplete {
+        subevent: u8,
+        status: u8,
+        handle: u16,
+        address: BdAddr,
+    },
+    LeMeta {
+        subevent: u8,
+    },
+    Event {
+        code: u8,
+    },
+    Acl {
+        handle: u16,
+        /// Packet boundary flag, 0b10 for the start of an L2CAP packet.
+        boundary: u8,
+        length: u16,
+        /// Header of the L2CAP packet, when the ACL packet starts one.
+        l2cap: Option<L2cap>,
+    },
+    Sco {
+        handle: u16,
+        length: u8,
+    },
+    Iso {
+        handle: u16,
+        length: u16,
+    },
+    /// Packet shorter than its header or than the fields decoded.
+    Invalid(Vec<u8>),
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([
+        *data.get(offset)?,
+        *data.get(offset + 1)?,
+    ]))
+}
+
+// HCI addresses are little endian.
+fn address_at(data: &[u8], offset: usize) -> Option<BdAddr> {
+    let mut address: [u8; 6] = data.get(offset..offset + 6)?.try_into().ok()?;
+    address.reverse();
+    Some(BdAddr::new(address))
+}
+
+impl Packet {
+    /// Decode an H4 packet, starting with its type.
+    pub fn decode(packet: &[u8]) -> Self {
+        Self::try_decode(packet).unwrap_or_else(|| Packet::Invalid(packet.to_vec()))
+    }
+
+    fn try_decode(packet: &[u8]) -> Option<Self> {
+        let (&packet_type, data) = packet.split_first()?;
+        Some(match PacketType::from_u8(packet_type)? {
+            PacketType::Command => Packet::Command {
+                opcode: u16_at(data, 0)?,
+            },
+            PacketType::Event => {
+                let code = *data.first()?;
+                let parameters = data.get(2..)?;
+                match code {
+                    0x03 => Packet::ConnectionComplete {
+                        status: *parameters.first()?,
+                        handle: u16_at(parameters, 1)? & 0x0fff,
+                        address: address_at(parameters, 3)?,
+                    },
+                    0x05 => Packet::DisconnectionComplete {
+                        status: *parameters.first()?,
+                        handle: u16_at(parameters, 1)? & 0x0fff,
+                        reason: *parameters.get(3)?,
+                    },
+                    0x0e => Packet::CommandComplete {
+                        opcode: u16_at(parameters, 1)?,
+                        status: parameters.get(3).copied(),
+                    },
+                    0x0f => Packet::CommandStatus {
+                        status: *parameters.first()?,
+                        opcode: u16_at(parameters, 2)?,
+                    },
+                    0x3e => match *parameters.first()? {
+                        subevent @ (0x01 | 0x0a) => Packet::LeConnectionComplete {
+                            subevent,
+                            status: *parameters.get(1)?,
+                            handle: u16_at(parameters, 2)? & 0x0fff,
+                            address: address_at(parameters, 6)?,
+                        },
+                        subevent => Packet::LeMeta { subevent },
+                    },
+                    code => Packet::Event { code },
+                }
+            }
+            PacketType::Acl => {
+                let header = u16_at(data, 0)?;
+                let boundary = ((header >> 12) & 0b11) as u8;
+                let payload = data.get(4..)?;
+                // Both the first non-flushable and the first
+                // flushable fragments start an L2CAP packet.
+                let l2cap = if boundary == 0b00 || boundary == 0b10 {
+                    Some(L2cap {
+                        length: u16_at(payload, 0)?,
+                        cid: u16_at(payload, 2)?,
+                    })
+                } else {
+                    None
+                };
+                Packet::Acl {
+                    handle: header & 0x0fff,
+                    boundary,
+                    length: u16_at(data, 2)?,
+                    l2cap,
+                }
+            }
+            PacketType::Sco => Packet::Sco {
+                handle: u16_at(data, 0)? & 0x0fff,
+                length: *data.get(2)?,
+            },
+            PacketType::Iso => Packet::Iso {
+                handle: u16_at(data, 0)? & 0x0fff,
+                length: u16_at(data, 2)? & 0x3fff,
+            },
+        })
+    }
+}
+
+/// Name of the command, from the specification.
+pub fn command_name(opcode: u16) -> Option<&'static str> {
+    Some(match opcode {
+        0x0000 => "NOP",
+        0x0401 => "Inquiry",
+        0x0402 => "Inquiry Cancel",
+        0x0405 => "Create Connection",
+        0x0406 => "Disconnect",
+        0x0409 => "Accept Connection Request",
+        0x040a => "Reject Connection Request",
+        0x040b => "Link Key Request Reply",
+        0x040c => "Link Key Request Negative Reply",
+        0x040d => "PIN Code Request Reply",
+        0x0411 => "Authentication Requested",
+        0x0413 => "Set Connection Encryption",
+        0x0419 => "Remote Name Request",
+        0x041b => "Read Remote Supported Features",
+        0x041c => "Read Remote Extended Features",
+        0x041d => "Read Remote Version Information",
+        0x0428 => "Setup Synchronous Connection",
+        0x0429 => "Accept Synchronous Connection Request",
+        0x042b => "IO Capability Request Reply",
+        0x042c => "User Confirmation Request Reply",
+        0x042d => "User Confirmation Request Negative Reply",
+        0x0434 => "IO Capability Request Negative Reply",
+        0x0803 => "Sniff Mode",
+        0x0804 => "Exit Sniff Mode",
+        0x080b => "Switch Role",
+        0x080d => "Write Link Policy Settings",
+        0x080f => "Write Default Link Policy Settings",
+        0x0c01 => "Set Event Mask",
+        0x0c03 => "Reset",
+        0x0c05 => "Set Event Filter",
+        0x0c0d => "Read Stored Link Key",
+        0x0c12 => "Delete Stored Link Key",
+        0x0c13 => "Write Local Name",
+        0x0c14 => "Read Local Name",
+        0x0c16 => "Write Connection Accept Timeout",
+        0x0c18 => "Write Page Timeout",
+        0x0c1a => "Write Scan Enable",
+        0x0c1c => "Write Page Scan Activity",
+        0x0c1e => "Write Inquiry Scan Activity",
+        0x0c23 => "Read Class of Device",
+        0x0c24 => "Write Class of Device",
+        0x0c25 => "Read Voice Setting",
+        0x0c26 => "Write Voice Setting",
+        0x0c2d => "Read Transmit Power Level",
+        0x0c2f => "Write Synchronous Flow Control Enable",
+        0x0c33 => "Host Buffer Size",
+        0x0c45 => "Write Inquiry Mode",
+        0x0c52 => "Write Extended Inquiry Response",
+        0x0c56 => "Write Simple Pairing Mode",
+        0x0c63 => "Set Event Mask Page 2",
+        0x0c6d => "Write LE Host Support",
+        0x0c7a => "Write Secure Connections Host Support",
+        0x1001 => "Read Local Version Information",
+        0x1002 => "Read Local Supported Commands",
+        0x1003 => "Read Local Supported Features",
+        0x1004 => "Read Local Extended Features",
+        0x1005 => "Read Buffer Size",
+        0x1009 => "Read BD_ADDR",
+        0x100b => "Read Local Supported Codecs",
+        0x2001 => "LE Set Event Mask",
+        0x2002 => "LE Read Buffer Size",
+        0x2003 => "LE Read Local Supported Features",
+        0x2005 => "LE Set Random Address",
+        0x2006 => "LE Set Advertising Parameters",
+        0x2008 => "LE Set Advertising Data",
+        0x2009 => "LE Set Scan Response Data",
+        0x200a => "LE Set Advertising Enable",
+        0x200b => "LE Set Scan Parameters",
+        0x200c => "LE Set Scan Enable",
+        0x200d => "LE Create Connection",
+        0x200e => "LE Create Connection Cancel",
+        0x200f => "LE Read Filter Accept List Size",
+        0x2010 => "LE Clear Filter Accept List",
+        0x2013 => "LE Connection Update",
+        0x2016 => "LE Read Remote Features",
+        0x2019 => "LE Enable Encryption",
+        0x201a => "LE Long Term Key Request Reply",
+        0x201b => "LE Long Term Key Request Negative Reply",
+        0x201c => "LE Read Supported States",
+        0x2022 => "LE Set Data Length",
+        0x2024 => "LE Write Suggested Default Data Length",
+        0x2027 => "LE Add Device To Resolving List",
+        0x2029 => "LE Clear Resolving List",
+        0x202d => "LE Set Address Resolution Enable",
+        0x202f => "LE Read Maximum Data Length",
+        0x2031 => "LE Set Default PHY",
+        0x2036 => "LE Set Extended Advertising Parameters",
+        0x2037 => "LE Set Extended Advertising Data",
+        0x2039 => "LE Set Extended Advertising Enable",
+        0x203b => "LE Read Number of Supported Advertising Sets",
+        _ => return None,
+    })
+}
+
+/// Name of the event, from the specification.
+pub fn event_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x01 => "Inquiry Complete",
+        0x02 => "Inquiry Result",
+        0x03 => "Connection Complete",
+        0x04 => "Connection Request",
+        0x05 => "Disconnection Complete",
+        0x06 => "Authentication Complete",
+        0x07 => "Remote Name Request Complete",
+        0x08 => "Encryption Change",
+        0x0b => "Read Remote Supported Features Complete",
+        0x0c => "Read Remote Version Information Complete",
+        0x0e => "Command Complete",
+        0x0f => "Command Status",
+        0x10 => "Hardware Error",
+        0x12 => "Role Change",
+        0x13 => "Number Of Completed Packets",
+        0x14 => "Mode Change",
+        0x16 => "PIN Code Request",
+        0x17 => "Link Key Request",
+        0x18 => "Link Key Notification",
+        0x1b => "Max Slots Change",
+        0x20 => "Page Scan Repetition Mode Change",
+        0x22 => "Inquiry Result with RSSI",
+        0x23 => "Read Remote Extended Features Complete",
+        0x2c => "Synchronous Connection Complete",
+        0x2d => "Synchronous Connection Changed",
+        0x2f => "Extended Inquiry Result",
+        0x30 => "Encryption Key Refresh Complete",
+        0x31 => "IO Capability Request",
+        0x32 => "IO Capability Response",
+        0x33 => "User Confirmation Request",
+        0x34 => "User Passkey Request",
+        0x36 => "Simple Pairing Complete",
+        0x38 => "Link Supervision Timeout Changed",
+        0x3d => "Remote Host Supported Features Notification",
+        0x3e => "LE Meta",
+        _ => return None,
+    })
+}
+
+/// Name of the LE Meta subevent, from the specification.
+pub fn le_subevent_name(subevent: u8) -> Option<&'static str> {
+    Some(match subevent {
+        0x01 => "LE Connection Complete",
+        0x02 => "LE Advertising Report",
+        0x03 => "LE Connection Update Complete",
+        0x04 => "LE Read Remote Features Complete",
+        0x05 => "LE Long Term Key Request",
+        0x06 => "LE Remote Connection Parameter Request",
+        0x07 => "LE Data Length Change",
+        0x0a => "LE Enhanced Connection Complete",
+        0x0b => "LE Directed Advertising Report",
+        0x0c => "LE PHY Update Complete",
+        0x0d => "LE Extended Advertising Report",
+        0x12 => "LE Advertising Set Terminated",
+        0x14 => "LE Channel Selection Algorithm",
+        _ => return None,
+    })
+}
+
+struct Command(u16);
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match command_name(self.0) {
+            Some(name) => write!(f, "{} ({:#06x})", name, self.0),
+            None => write!(
+                f,
+                "Command {:#06x} (OGF {:#04x}, OCF {:#05x})",
+                self.0,
+                self.0 >> 10,
+                self.0 & 0x03ff
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Packet::Command { opcode } => write!(f, "{}", Command(*opcode)),
+            Packet::CommandComplete { opcode, status } => {
+                write!(f, "Command Complete {}", Command(*opcode))?;
+                match status {
+                    Some(status) => write!(f, " status={:#04x}", status),
+                    None => Ok(()),
+                }
+            }
+            Packet::CommandStatus { opcode, status } => write!(
+                f,
+                "Command Status {} status={:#04x}",
+                Command(*opcode),
+                status
+            ),
+            Packet::ConnectionComplete {
+                status,
+                handle,
+                address,
+            } => write!(
+                f,
+                "Connection Complete handle={:#06x} address={} status={:#04x}",
+                handle, address, status
+            ),
+            Packet::DisconnectionComplete {
+                status,
+                handle,
+                reason,
+            } => write!(
+                f,
+                "Disconnection Complete handle={:#06x} reason={:#04x} status={:#04x}",
+                handle, reason, status
+            ),
+            Packet::LeConnectionComplete {
+                subevent,
+                status,
+                handle,
+                address,
+            } => write!(
+                f,
+                "{} handle={:#06x} address={} status={:#04x}",
+                le_subevent_name(*subevent).unwrap(),
+                handle,
+                address,
+                status
+            ),
+            Packet::LeMeta { subevent } => match le_subevent_name(*subevent) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "LE Meta subevent {:#04x}", subevent),
+            },
+            Packet::Event { code } => match event_name(*code) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "Event {:#04x}", code),
+            },
+            Packet::Acl {
+                handle,
+                boundary,
+                length,
+                l2cap,
+            } => {
+                write!(f, "ACL handle={:#06x} length={}", handle, length)?;
+                match l2cap {
+                    Some(L2cap { length, cid }) => {
+                        write!(f, " L2CAP cid={:#06x} length={}", cid, length)
+                    }
+                    None if *boundary == 0b01 => write!(f, " continuation"),
+                    None => Ok(()),
+                }
+            }
+            Packet::Sco { handle, length } => {
+                write!(f, "SCO handle={:#06x} length={}", handle, length)
+            }
+            Packet::Iso { handle, length } => {
+                write!(f, "ISO handle={:#06x} length={}", handle, length)
+            }
+            Packet::Invalid(packet) => {
+                write!(f, "Invalid packet ")?;
+                for byte in packet {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{L2cap, Packet};
+    use crate::bd_addr::BdAddr;
+
+    #[test]
+    fn test_decode() {
+        let decode = |packet: &[u8]| Packet::decode(packet);
+
+        assert_eq!(
+            decode(&[0x01, 0x03, 0x0c, 0x00]),
+            Packet::Command { opcode: 0x0c03 }
+        );
+        assert_eq!(
+            decode(&[0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00]),
+            Packet::CommandComplete {
+                opcode: 0x0c03,
+                status: Some(0)
+            }
+        );
+        assert_eq!(
+            decode(&[0x04, 0x0f, 0x04, 0x00, 0x01, 0x05, 0x04]),
+            Packet::CommandStatus {
+                opcode: 0x0405,
+                status: 0
+            }
+        );
+        assert_eq!(
+            decode(&[
+                0x04, 0x03, 0x0b, 0x00, 0x40, 0x00, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x01, 0x00
+            ]),
+            Packet::ConnectionComplete {
+                status: 0,
+                handle: 0x0040,
+                address: BdAddr::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+            }
+        );
+        assert_eq!(
+            decode(&[0x04, 0x05, 0x04, 0x00, 0x40, 0x00, 0x13]),
+            Packet::DisconnectionComplete {
+                status: 0,
+                handle: 0x0040,
+                reason: 0x13
+            }
+        );
+        assert_eq!(
+            decode(&[
+                0x04, 0x3e, 0x13, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x66, 0x55, 0x44, 0x33, 0x22,
+                0x11, 0x28, 0x00, 0x00, 0x00, 0xd0, 0x07, 0x00
+            ]),
+            Packet::LeConnectionComplete {
+                subevent: 0x01,
+                status: 0,
+                handle: 0x0001,
+                address: BdAddr::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+            }
+        );
+        assert_eq!(
+            decode(&[0x04, 0x3e, 0x02, 0x0c, 0x00]),
+            Packet::LeMeta { subevent: 0x0c }
+        );
+        assert_eq!(
+            decode(&[0x02, 0x40, 0x20, 0x06, 0x00, 0x02, 0x00, 0x01, 0x00, 0x0a, 0x01]),
+            Packet::Acl {
+                handle: 0x0040,
+                boundary: 0b10,
+                length: 6,
+                l2cap: Some(L2cap {
+                    length: 2,
+                    cid: 0x0001
+                }),
+            }
+        );
+        assert_eq!(
+            decode(&[0x02, 0x40, 0x10, 0x01, 0x00, 0xaa]),
+            Packet::Acl {
+                handle: 0x0040,
+                boundary: 0b01,
+                length: 1,
+                l2cap: None,
+            }
+        );
+        assert_eq!(
+            decode(&[0x04, 0x05, 0x01, 0x00]),
+            Packet::Invalid(vec![0x04, 0x05, 0x01, 0x00])
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let display = |packet: &[u8]| Packet::decode(packet).to_string();
+
+        assert_eq!(display(&[0x01, 0x03, 0x0c, 0x00]), "Reset (0x0c03)");
+        assert_eq!(
+            display(&[0x01, 0x01, 0xfc, 0x00]),
+            "Command 0xfc01 (OGF 0x3f, OCF 0x001)"
+        );
+        assert_eq!(
+            display(&[0x04, 0x0e, 0x04, 0x01, 0x09, 0x10, 0x00]),
+            "Command Complete Read BD_ADDR (0x1009) status=0x00"
+        );
+        assert_eq!(
+            display(&[0x04, 0x05, 0x04, 0x00, 0x40, 0x00, 0x13]),
+            "Disconnection Complete handle=0x0040 reason=0x13 status=0x00"
+        );
+        assert_eq!(display(&[0x04, 0x13, 0x00]), "Number Of Completed Packets");
+        assert_eq!(
+            display(&[0x02, 0x40, 0x20, 0x06, 0x00, 0x02, 0x00, 0x01, 0x00, 0x0a, 0x01]),
+            "ACL handle=0x0040 length=6 L2CAP cid=0x0001 length=2"
+        );
+    }
+}
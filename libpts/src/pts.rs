@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::io::Write;
+use std::path::Path;
 use std::process::{Child, Stdio};
 
 use async_io::Async;
@@ -25,6 +26,7 @@ use serde_repr::Deserialize_repr;
 use crate::bd_addr::BdAddr;
 use crate::hci::WineHCIPort;
 use crate::installer::PTS_PATH;
+use crate::wine::Wine;
 
 #[derive(Deserialize_repr, PartialEq, Debug)]
 #[repr(u8)]
@@ -95,17 +97,25 @@ pub enum Message {
     Raw(String),
 }
 
+impl Message {
+    /// Parse a line printed by the server.
+    pub fn parse(line: String) -> Self {
+        serde_json::from_str(&line).unwrap_or(Message::Raw(line))
+    }
+}
+
 pub struct Server<'wine>(Child, #[allow(dead_code)] WineHCIPort<'wine>);
 
 impl<'wine> Server<'wine> {
     pub fn spawn<'a>(
-        port: WineHCIPort<'wine>,
+        wine: &'wine Wine,
+        hci: &Path,
         profile: &str,
         test_case: &str,
         parameters: impl Iterator<Item = (&'a str, &'a str, &'a str)>,
         audio_output_path: Option<&str>,
     ) -> Self {
-        let wine = &port.wine;
+        let port = WineHCIPort::bind(wine, hci).expect("HCI port");
         let dir = wine.drive_c().join(PTS_PATH).join("bin");
 
         let process = wine
@@ -123,8 +133,11 @@ impl<'wine> Server<'wine> {
         Self(process, port)
     }
 
+    /// Split the server into its stream of messages and its answer
+    /// sender. `raw` is called with each line printed by the server.
     pub fn into_parts(
         mut self,
+        mut raw: impl FnMut(&str) + 'wine,
     ) -> (
         impl Stream<Item = std::io::Result<Message>> + 'wine,
         impl FnMut(&str) + 'wine,
@@ -133,13 +146,10 @@ impl<'wine> Server<'wine> {
         let stdout = BufReader::new(Async::new(stdout).unwrap());
 
         (
-            stdout.lines().map(|result| {
+            stdout.lines().map(move |result| {
                 result.map(|line| {
-                    if let Ok(message) = serde_json::from_str(&line) {
-                        message
-                    } else {
-                        Message::Raw(line)
-                    }
+                    raw(&line);
+                    Message::parse(line)
                 })
             }),
             move |answer| {
@@ -22,6 +22,7 @@ use nom::{
     IResult,
 };
 
+use serde::Serialize;
 use termion::{color, style};
 
 use std::borrow::Cow;
@@ -29,7 +30,7 @@ use std::fmt;
 
 use crate::at;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub enum TTCNValue {
     Identifier(String),
     Integer(String),
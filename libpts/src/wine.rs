@@ -252,6 +252,30 @@ impl Wine {
         command
     }
 
+    /// Copy the prefix to `destination`, replacing its previous content.
+    pub fn copy_prefix(&self, destination: &Path) -> io::Result<()> {
+        match fs::remove_dir_all(destination) {
+            // Ignore NotFound error as this mean the directory already don't exist
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            v => v,
+        }?;
+
+        // Use cp to preserve the dosdevices symlinks (z: points to /)
+        // and to benefit from copy on write on supporting filesystems
+        let status = Command::new("cp")
+            .arg("-a")
+            .arg("--reflink=auto")
+            .arg(&self.prefix)
+            .arg(destination)
+            .status()?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("cp {}", status)))
+        }
+    }
+
     pub fn devices(&self) -> io::Result<Vec<String>> {
         fs::read_dir(self.prefix.join("dosdevices"))?
             .map(|res| {
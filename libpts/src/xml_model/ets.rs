@@ -14,10 +14,7 @@
 
 use serde::Deserialize;
 
-use evalexpr::error::EvalexprResult;
-use evalexpr::eval_boolean_with_context;
-
-use super::fn_context::FnContext;
+use crate::mapping::Expression;
 
 use super::XMLModel;
 
@@ -42,7 +39,6 @@ pub struct Profile {
 #[derive(Debug, Deserialize)]
 pub struct Group {
     #[serde(rename = "Name")]
-    #[allow(dead_code)]
     pub name: String,
     #[serde(rename = "Group", default)]
     pub groups: Vec<Group>,
@@ -57,16 +53,24 @@ pub struct TestCase {
     #[serde(rename = "Mapping", default)]
     pub mapping: String,
     #[serde(rename = "Description", default)]
-    #[allow(dead_code)]
     pub description: String,
 }
 
 impl TestCase {
-    pub fn is_enabled<F: Fn(&str) -> Option<bool>>(&self, get_value: &F) -> EvalexprResult<bool> {
-        let mut mapping = self.mapping.replace("AND", "&&");
-        mapping = mapping.replace("OR", "||");
-
-        eval_boolean_with_context(&mapping, &FnContext(get_value))
+    /// The test case is enabled when its mapping evaluates to true,
+    /// an invalid mapping, or one with any unresolved item, disables
+    /// it even when the other items decide its value.
+    pub fn is_enabled<F: Fn(&str) -> Option<bool>>(&self, get_value: &F) -> bool {
+        Expression::parse(&self.mapping)
+            .ok()
+            .filter(|expression| {
+                expression
+                    .items()
+                    .iter()
+                    .all(|name| get_value(name).is_some())
+            })
+            .and_then(|expression| expression.evaluate(get_value))
+            .unwrap_or(false)
     }
 }
 
@@ -78,6 +82,28 @@ impl Group {
                 .chain(self.groups.iter().flat_map(|group| group.testcases())),
         )
     }
+
+    fn collect<'a>(&'a self, path: &mut Vec<&'a str>, out: &mut Vec<(Vec<&'a str>, &'a TestCase)>) {
+        path.push(&self.name);
+        for testcase in self.testcases.iter() {
+            out.push((path.clone(), testcase));
+        }
+        for group in self.groups.iter() {
+            group.collect(path, out);
+        }
+        path.pop();
+    }
+
+    fn path_to<'a>(&'a self, testcase: &str, path: &mut Vec<&'a str>) -> bool {
+        path.push(&self.name);
+        if self.testcases.iter().any(|t| t.name == testcase)
+            || self.groups.iter().any(|group| group.path_to(testcase, path))
+        {
+            return true;
+        }
+        path.pop();
+        false
+    }
 }
 
 impl XMLModel<'_> for Ets {
@@ -90,10 +116,30 @@ impl Ets {
         get_value: F,
     ) -> impl Iterator<Item = String> + 'a {
         self.testcases()
-            .filter(move |testcase| testcase.is_enabled(&get_value).unwrap_or(false))
+            .filter(move |testcase| testcase.is_enabled(&get_value))
             .map(|testcase| testcase.name.clone())
     }
 
+    /// Names of the groups containing the test case, outermost first.
+    pub fn groups_of(&self, testcase: &str) -> Option<Vec<&str>> {
+        let mut path = Vec::new();
+        self.profile
+            .groups
+            .iter()
+            .any(|group| group.path_to(testcase, &mut path))
+            .then_some(path)
+    }
+
+    /// Test cases with the names of the groups containing them,
+    /// outermost first.
+    pub fn testcases_with_groups(&self) -> Vec<(Vec<&str>, &TestCase)> {
+        let mut testcases = Vec::new();
+        for group in self.profile.groups.iter() {
+            group.collect(&mut Vec::new(), &mut testcases);
+        }
+        testcases
+    }
+
     fn testcases(&self) -> impl Iterator<Item = &TestCase> + '_ {
         self.profile
             .groups
@@ -101,3 +147,65 @@ impl Ets {
             .flat_map(|group| group.testcases())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Ets, TestCase};
+    use serde_xml_rs::from_str;
+
+    #[test]
+    fn is_enabled() {
+        let testcase = TestCase {
+            name: String::from("A2DP/SRC/AS/BV-01-I"),
+            mapping: String::from("TSPC_A2DP_2_1 OR TSPC_A2DP_2_2"),
+            description: String::new(),
+        };
+        let values = |a, b| {
+            move |name: &str| match name {
+                "TSPC_A2DP_2_1" => a,
+                "TSPC_A2DP_2_2" => b,
+                _ => None,
+            }
+        };
+
+        assert!(testcase.is_enabled(&values(Some(true), Some(false))));
+        assert!(!testcase.is_enabled(&values(Some(false), Some(false))));
+        // An unresolved item disables the test, even when the others
+        // are enough to evaluate the mapping.
+        assert!(!testcase.is_enabled(&values(Some(true), None)));
+    }
+
+    #[test]
+    fn testcases_with_groups() {
+        let ets_xml = r#"
+        <ETS>
+            <Profile>
+                <Name>A2DP</Name>
+                <Group>
+                    <Name>SRC</Name>
+                    <TestCase>
+                        <Name>A2DP/SRC/CC/BV-09-I</Name>
+                        <Mapping>TSPC_A2DP_2_1</Mapping>
+                    </TestCase>
+                    <Group>
+                        <Name>AS</Name>
+                        <TestCase>
+                            <Name>A2DP/SRC/AS/BV-01-I</Name>
+                            <Mapping>TSPC_A2DP_2_1 AND TSPC_A2DP_2_2</Mapping>
+                            <Description>Verify streaming.</Description>
+                        </TestCase>
+                    </Group>
+                </Group>
+            </Profile>
+        </ETS>"#;
+        let ets: Ets = from_str(ets_xml).unwrap();
+        let testcases = ets.testcases_with_groups();
+
+        assert_eq!(testcases.len(), 2);
+        assert_eq!(testcases[0].0, ["SRC"]);
+        assert_eq!(testcases[0].1.name, "A2DP/SRC/CC/BV-09-I");
+        assert_eq!(testcases[1].0, ["SRC", "AS"]);
+        assert_eq!(testcases[1].1.description, "Verify streaming.");
+        assert_eq!(ets.groups_of("A2DP/SRC/AS/BV-01-I").unwrap(), ["SRC", "AS"]);
+    }
+}
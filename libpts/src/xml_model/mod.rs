@@ -13,7 +13,6 @@
 // limitations under the License.
 
 pub mod ets;
-mod fn_context;
 pub mod picsx;
 pub mod pixitx;
 
@@ -49,9 +48,12 @@ pub trait XMLModel<'a>: Deserialize<'a> {
         let content: String = read_to_string(path.clone()).map_err(|err| {
             Error::FileNotFound(err, String::from(path.to_str().unwrap_or("Unknown")))
         })?;
+        Self::from_str(&content)
+    }
 
+    fn from_str(content: &str) -> Result<Self, Error> {
         // Strip BOM if present as it's not accepted by the xml parser
-        let content = content.strip_prefix('\u{feff}').unwrap_or(&*content);
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
 
         let mut de =
             Deserializer::new_from_reader(content.as_bytes()).non_contiguous_seq_elements(true);
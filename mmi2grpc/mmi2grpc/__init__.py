@@ -28,10 +28,12 @@ MAX_RETRIES = 10
 
 class IUT:
     def __init__(
-            self, test: str, args: List[str], port: int = GRPC_PORT, **kwargs):
+            self, test: str, args: List[str], port: int = GRPC_PORT,
+            job: int = 0, **kwargs):
         self.a2dp_ = None
         self.address_ = None
-        self.port = port
+        # Each parallel job drives its own IUT, listening on the next port.
+        self.port = port + job
         self.test = test
 
     def __enter__(self):
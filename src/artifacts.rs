@@ -0,0 +1,169 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use libpts::{Event, Interaction};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::baseline::Verdict;
+use crate::btsnoop::{self, Capture};
+use crate::test::TestResult;
+
+/// Directory of the artifacts of a test, relative to the artifacts root.
+/// The first attempt writes into it directly, each retry into its
+/// own `retry-N` subdirectory.
+pub fn directory(root: &Path, test: &str, retry: usize) -> PathBuf {
+    let directory = test
+        .split('/')
+        .fold(root.to_owned(), |path, part| path.join(part));
+    if retry == 0 {
+        directory
+    } else {
+        directory.join(format!("retry-{}", retry))
+    }
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    stack: &'a [String],
+}
+
+/// Files recorded during one attempt of a test:
+/// - `server.jsonl`, the lines printed by the PTS server,
+/// - `events.jsonl`, the parsed events with their step stack,
+/// - `mmi.jsonl`, the MMI and the answers of the IUT,
+/// - `hci.btsnoop`, the HCI packets between the PTS and the controller,
+/// - `verdict.json`, the result of the attempt.
+pub struct Artifacts {
+    directory: PathBuf,
+    events: BufWriter<File>,
+    mmi: Arc<Mutex<BufWriter<File>>>,
+    capture: Capture,
+}
+
+impl Artifacts {
+    pub fn create(directory: PathBuf) -> io::Result<Self> {
+        // Remove what a previous run left, including its retries.
+        match fs::remove_dir_all(&directory) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        fs::create_dir_all(&directory)?;
+
+        Ok(Artifacts {
+            events: BufWriter::new(File::create(directory.join("events.jsonl"))?),
+            mmi: Arc::new(Mutex::new(BufWriter::new(File::create(
+                directory.join("mmi.jsonl"),
+            )?))),
+            capture: Arc::new(Mutex::new(btsnoop::Writer::new(BufWriter::new(
+                File::create(directory.join("hci.btsnoop"))?,
+            ))?)),
+            directory,
+        })
+    }
+
+    /// Writer of the server output, it is moved into the test run.
+    pub fn server_output(&self) -> io::Result<BufWriter<File>> {
+        Ok(BufWriter::new(File::create(
+            self.directory.join("server.jsonl"),
+        )?))
+    }
+
+    pub fn mmi(&self) -> MmiLog {
+        MmiLog(self.mmi.clone())
+    }
+
+    pub fn capture(&self) -> Capture {
+        self.capture.clone()
+    }
+
+    pub fn event(&mut self, event: &Event, stack: &[String]) -> io::Result<()> {
+        serde_json::to_writer(&mut self.events, &EventRecord { event, stack })?;
+        writeln!(self.events)
+    }
+
+    pub fn verdict(
+        mut self,
+        test: &str,
+        result: &TestResult,
+        duration: Duration,
+    ) -> io::Result<()> {
+        self.events.flush()?;
+        self.mmi.lock().unwrap().flush()?;
+        self.capture.lock().unwrap().flush()?;
+
+        let mut verdict = json!({
+            "test": test,
+            "verdict": Verdict::from(result),
+            "duration": duration.as_secs_f64(),
+        });
+        if let TestResult::Error(e) = result {
+            verdict["error"] = json!(format!("{:?}", e));
+        }
+
+        let mut file = File::create(self.directory.join("verdict.json"))?;
+        serde_json::to_writer_pretty(&mut file, &verdict)?;
+        writeln!(file)
+    }
+}
+
+/// Log of the MMI exchanges, shared with the IUT interactions.
+#[derive(Clone)]
+pub struct MmiLog(Arc<Mutex<BufWriter<File>>>);
+
+impl MmiLog {
+    pub fn record<E: Display>(&self, interaction: &Interaction, answer: &Result<String, E>) {
+        let (_, style, id, _, _, description) = interaction.explode();
+        let mut record = json!({
+            "id": id,
+            "style": format!("{:?}", style),
+            "description": description,
+        });
+        match answer {
+            Ok(answer) => record["answer"] = json!(answer),
+            Err(e) => record["error"] = json!(e.to_string()),
+        }
+
+        let mut file = self.0.lock().unwrap();
+        let _ = writeln!(file, "{}", record);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::directory;
+    use std::path::Path;
+
+    #[test]
+    fn test_directory() {
+        let root = Path::new("/tmp/artifacts");
+        assert_eq!(
+            directory(root, "A2DP/SRC/AS/BV-01-I", 0),
+            Path::new("/tmp/artifacts/A2DP/SRC/AS/BV-01-I")
+        );
+        assert_eq!(
+            directory(root, "A2DP/SRC/AS/BV-01-I", 2),
+            Path::new("/tmp/artifacts/A2DP/SRC/AS/BV-01-I/retry-2")
+        );
+    }
+}
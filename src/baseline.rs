@@ -0,0 +1,271 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use termion::{color, style};
+
+use crate::jsonc;
+use crate::test::{TestExecution, TestResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Verdict {
+    Pass,
+    Fail,
+    Inconc,
+    None,
+    Error,
+}
+
+impl From<&TestResult> for Verdict {
+    fn from(result: &TestResult) -> Self {
+        match result {
+            TestResult::Pass | TestResult::Flaky => Verdict::Pass,
+            TestResult::Fail => Verdict::Fail,
+            TestResult::Inconclusive => Verdict::Inconc,
+            TestResult::None | TestResult::Aborted => Verdict::None,
+            TestResult::Error(_) => Verdict::Error,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verdict = match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail => "FAIL",
+            Verdict::Inconc => "INCONC",
+            Verdict::None => "NONE",
+            Verdict::Error => "ERROR",
+        };
+        f.write_str(verdict)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Expectation {
+    pub verdict: Verdict,
+    /// Why the test is not expected to pass.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Link to the bug tracking the failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bug: Option<String>,
+}
+
+/// Expected verdict of each test, tests missing from the
+/// baseline are expected to pass.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Baseline {
+    tests: BTreeMap<String, Expectation>,
+}
+
+/// Test whose verdict differs from the baseline.
+pub struct Mismatch<'a> {
+    pub test: &'a str,
+    pub expected: Verdict,
+    pub actual: Verdict,
+    pub expectation: Option<&'a Expectation>,
+}
+
+impl Mismatch<'_> {
+    pub fn is_regression(&self) -> bool {
+        self.expected == Verdict::Pass
+    }
+
+    pub fn is_unexpected_pass(&self) -> bool {
+        self.actual == Verdict::Pass
+    }
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = BufReader::new(File::open(path).context("Failed to open baseline")?);
+        jsonc::from_reader(file).context("Failed to parse baseline")
+    }
+
+    /// Same as `load`, but a missing file is an empty baseline.
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        match File::open(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Default::default()),
+            _ => Self::load(path),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path).context("Failed to create baseline")?);
+        serde_json::to_writer_pretty(&mut file, self)?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    pub fn compare<'a>(&'a self, results: &'a [TestExecution]) -> Vec<Mismatch<'a>> {
+        results
+            .iter()
+            .filter_map(|execution| {
+                let expectation = self.tests.get(&execution.name);
+                let expected = expectation.map_or(Verdict::Pass, |e| e.verdict);
+                let actual = Verdict::from(&execution.result);
+                (expected != actual).then_some(Mismatch {
+                    test: &execution.name,
+                    expected,
+                    actual,
+                    expectation,
+                })
+            })
+            .collect()
+    }
+
+    /// Record the verdicts of the run. The reason and bug of a test
+    /// are kept until it passes, tests which did not run are kept as is.
+    pub fn update(&mut self, results: &[TestExecution]) {
+        for execution in results {
+            if matches!(execution.result, TestResult::Aborted) {
+                continue;
+            }
+            let verdict = Verdict::from(&execution.result);
+            let expectation = self
+                .tests
+                .entry(execution.name.clone())
+                .or_insert(Expectation {
+                    verdict,
+                    reason: None,
+                    bug: None,
+                });
+            expectation.verdict = verdict;
+            if verdict == Verdict::Pass {
+                expectation.reason = None;
+                expectation.bug = None;
+            }
+        }
+    }
+}
+
+pub fn report(mismatches: &[Mismatch]) {
+    if mismatches.is_empty() {
+        println!("All results match the baseline");
+        return;
+    }
+
+    println!("\n{}Baseline{}:", style::Bold, style::Reset);
+    for mismatch in mismatches {
+        let label = if mismatch.is_regression() {
+            format!("{}regression", color::Fg(color::Red))
+        } else if mismatch.is_unexpected_pass() {
+            format!("{}unexpected pass", color::Fg(color::Yellow))
+        } else {
+            format!("{}changed", color::Fg(color::Cyan))
+        };
+        print!(
+            "  {}{}: {} expected {}, got {}",
+            label,
+            style::Reset,
+            mismatch.test,
+            mismatch.expected,
+            mismatch.actual
+        );
+        if let Some(expectation) = mismatch.expectation {
+            if let Some(ref reason) = expectation.reason {
+                print!(" ({})", reason);
+            }
+            if let Some(ref bug) = expectation.bug {
+                print!(" [{}]", bug);
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Baseline, Verdict};
+    use crate::test::{TestExecution, TestResult};
+    use std::time::Duration;
+
+    fn execution(name: &str, result: TestResult) -> TestExecution {
+        TestExecution {
+            profile: String::from("A2DP"),
+            groups: Vec::new(),
+            name: name.to_owned(),
+            result,
+            duration: Duration::ZERO,
+            log: String::new(),
+            retries: Vec::new(),
+        }
+    }
+
+    fn baseline() -> Baseline {
+        serde_json::from_str(
+            r#"{
+                "A2DP/SRC/CC/BV-09-I": { "verdict": "FAIL", "reason": "Codec", "bug": "b/1" },
+                "A2DP/SRC/AS/BV-02-I": { "verdict": "INCONC" },
+                "A2DP/SRC/REL/BV-01-I": { "verdict": "PASS" }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = baseline();
+        let results = [
+            execution("A2DP/SRC/AS/BV-01-I", TestResult::Fail),
+            execution("A2DP/SRC/CC/BV-09-I", TestResult::Fail),
+            execution("A2DP/SRC/AS/BV-02-I", TestResult::Flaky),
+            execution("A2DP/SRC/REL/BV-01-I", TestResult::Pass),
+        ];
+
+        let mismatches = baseline.compare(&results);
+        assert_eq!(mismatches.len(), 2);
+
+        assert_eq!(mismatches[0].test, "A2DP/SRC/AS/BV-01-I");
+        assert!(mismatches[0].is_regression());
+        assert_eq!(mismatches[0].actual, Verdict::Fail);
+
+        assert_eq!(mismatches[1].test, "A2DP/SRC/AS/BV-02-I");
+        assert!(mismatches[1].is_unexpected_pass());
+        assert!(!mismatches[1].is_regression());
+    }
+
+    #[test]
+    fn test_update() {
+        let mut baseline = baseline();
+        baseline.update(&[
+            execution("A2DP/SRC/CC/BV-09-I", TestResult::Inconclusive),
+            execution("A2DP/SRC/AS/BV-02-I", TestResult::Pass),
+            execution("A2DP/SRC/AS/BV-01-I", TestResult::Fail),
+            execution("A2DP/SRC/REL/BV-01-I", TestResult::Aborted),
+            execution("A2DP/SRC/REL/BV-02-I", TestResult::Aborted),
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(&baseline).unwrap(),
+            serde_json::json!({
+                "A2DP/SRC/AS/BV-01-I": { "verdict": "FAIL" },
+                "A2DP/SRC/AS/BV-02-I": { "verdict": "PASS" },
+                "A2DP/SRC/CC/BV-09-I": { "verdict": "INCONC", "reason": "Codec", "bug": "b/1" },
+                "A2DP/SRC/REL/BV-01-I": { "verdict": "PASS" }
+            })
+        );
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Capture of the HCI packets in the btsnoop format read by Wireshark.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_lite::{ready, AsyncWrite};
+pub use libpts::h4::Direction;
+use libpts::h4::{PacketType, Reassembler};
+
+/// H4 datalink, the packets start with their type.
+const DATALINK_H4: u32 = 1002;
+
+/// Microseconds from 0 AD to the Unix epoch.
+const EPOCH_OFFSET: u64 = 0x00dc_ddb3_0f2f_8000;
+
+pub struct Writer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(b"btsnoop\0")?;
+        writer.write_all(&1u32.to_be_bytes())?;
+        writer.write_all(&DATALINK_H4.to_be_bytes())?;
+        Ok(Writer { writer })
+    }
+
+    /// Write a record of `packet`, an H4 packet including its type.
+    pub fn record(
+        &mut self,
+        packet: &[u8],
+        direction: Direction,
+        time: SystemTime,
+    ) -> io::Result<()> {
+        let mut flags = match direction {
+            Direction::Sent => 0u32,
+            Direction::Received => 1,
+        };
+        if matches!(
+            packet.first().copied().and_then(PacketType::from_u8),
+            Some(PacketType::Command) | Some(PacketType::Event)
+        ) {
+            flags |= 2;
+        }
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_micros() as u64)
+            + EPOCH_OFFSET;
+
+        let length = packet.len() as u32;
+        self.writer.write_all(&length.to_be_bytes())?;
+        self.writer.write_all(&length.to_be_bytes())?;
+        self.writer.write_all(&flags.to_be_bytes())?;
+        // Cumulative drops
+        self.writer.write_all(&0u32.to_be_bytes())?;
+        self.writer.write_all(&timestamp.to_be_bytes())?;
+        self.writer.write_all(packet)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Capture file shared by both directions of the HCI link.
+pub type Capture = Arc<Mutex<Writer<BufWriter<File>>>>;
+
+/// Writer recording the packets written to `writer` into a capture,
+/// as they are sent after any fault injection.
+pub struct Tap<T, W: Write> {
+    writer: T,
+    direction: Direction,
+    reassembler: Reassembler,
+    capture: Option<Arc<Mutex<Writer<W>>>>,
+}
+
+impl<T, W: Write> Tap<T, W> {
+    pub fn new(writer: T, direction: Direction, capture: Arc<Mutex<Writer<W>>>) -> Self {
+        Tap {
+            writer,
+            direction,
+            reassembler: Reassembler::default(),
+            capture: Some(capture),
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin, W: Write> AsyncWrite for Tap<T, W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let written = ready!(Pin::new(&mut self.writer).poll_write(cx, buf))?;
+
+        let this = &mut *self;
+        if let Some(capture) = &this.capture {
+            let time = SystemTime::now();
+            let mut capture = capture.lock().unwrap();
+            let result = this
+                .reassembler
+                .push(&buf[..written])
+                .iter()
+                .try_for_each(|packet| capture.record(packet, this.direction, time));
+            // A broken capture should not break the link.
+            if let Err(e) = result {
+                eprintln!("Failed to write the HCI capture: {}", e);
+                drop(capture);
+                this.capture = None;
+            }
+        }
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Direction, Tap, Writer};
+    use async_io::block_on;
+    use futures_lite::AsyncWriteExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_record() {
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer
+            .record(
+                &[0x01, 0x03, 0x0c, 0x00],
+                Direction::Sent,
+                UNIX_EPOCH + Duration::from_micros(1),
+            )
+            .unwrap();
+        writer
+            .record(
+                &[0x02, 0x40, 0x00, 0x00, 0x00],
+                Direction::Received,
+                UNIX_EPOCH,
+            )
+            .unwrap();
+
+        let mut expected = b"btsnoop\0\0\0\0\x01\0\0\x03\xea".to_vec();
+        expected.extend([0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, 0]);
+        expected.extend([0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x2f, 0x80, 0x01]);
+        expected.extend([0x01, 0x03, 0x0c, 0x00]);
+        expected.extend([0, 0, 0, 5, 0, 0, 0, 5, 0, 0, 0, 1, 0, 0, 0, 0]);
+        expected.extend([0x00, 0xdc, 0xdd, 0xb3, 0x0f, 0x2f, 0x80, 0x00]);
+        expected.extend([0x02, 0x40, 0x00, 0x00, 0x00]);
+        assert_eq!(writer.writer, expected);
+    }
+
+    #[test]
+    fn test_tap() {
+        let capture = Arc::new(Mutex::new(Writer::new(Vec::new()).unwrap()));
+        let input: &[u8] = &[0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00, 0x04];
+        let mut tap = Tap::new(Vec::new(), Direction::Received, capture.clone());
+
+        block_on(tap.write_all(input)).unwrap();
+        assert_eq!(tap.writer, input);
+
+        // Only the complete event is recorded
+        let capture = capture.lock().unwrap();
+        assert_eq!(capture.writer.len(), 16 + 24 + 7);
+        assert_eq!(capture.writer[16 + 8..16 + 12], [0, 0, 0, 3]);
+    }
+}
@@ -0,0 +1,714 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, stdout, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+use libpts::{EtsTestCase, IcsSource, Profile, PTS};
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::config::Config;
+use crate::explain;
+use crate::impact::{self, Cause, Change};
+use crate::selector::Selector;
+use crate::solver::{self, Item, Solution};
+use crate::template;
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// List the installed profiles
+    Profiles {
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// List the test cases of a profile, enabled or not
+    Tests {
+        profile: String,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Show the description and ICS mapping of a test
+    Describe {
+        test: String,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Explain why a test is enabled or not, from the value
+    /// of each ICS item of its mapping
+    Explain {
+        test: String,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// List the ICS of a profile with their default values
+    Ics {
+        profile: String,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// List the IXIT of a profile with their types and default values
+    Pixit {
+        profile: String,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Show the tests enabled and disabled when replacing the ICS
+    /// of a config with the ones of another, and the items causing it
+    IcsDiff {
+        /// Current config file
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        /// New config file
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+        /// Only compare this profile, can be repeated.
+        /// Defaults to all the installed profiles
+        #[structopt(short, long, number_of_values = 1)]
+        profile: Vec<String>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Find the fewest ICS items to change from their default so that
+    /// the selected tests are enabled, keeping the ones of the config
+    Solve {
+        /// Selects the target tests, like the selectors of a run
+        #[structopt(required = true)]
+        tests: Vec<String>,
+        #[structopt(flatten)]
+        output: Output,
+    },
+    /// Print a config with every ICS and IXIT of the profiles set to
+    /// its default value, and their descriptions as comments
+    Template {
+        #[structopt(required = true)]
+        profiles: Vec<String>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub struct Output {
+    /// Output format, table or json
+    #[structopt(long, default_value = "table")]
+    format: Format,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Table,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            _ => bail!("Unknown format '{}', expected table or json", format),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Test<'a> {
+    name: &'a str,
+    groups: &'a [&'a str],
+    enabled: bool,
+    mapping: &'a str,
+    description: &'a str,
+}
+
+impl<'a> From<&'a EtsTestCase<'a>> for Test<'a> {
+    fn from(testcase: &'a EtsTestCase<'a>) -> Self {
+        Test {
+            name: testcase.name,
+            groups: &testcase.groups,
+            enabled: testcase.enabled,
+            mapping: testcase.mapping,
+            description: testcase.description,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Ics<'a> {
+    name: &'a str,
+    default: bool,
+    mandatory: bool,
+    description: &'a str,
+}
+
+#[derive(Serialize)]
+struct Ixit<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    value_type: &'a str,
+    default: &'a str,
+    description: &'a str,
+}
+
+#[derive(Serialize)]
+struct ProfileChanges<'a> {
+    profile: &'a str,
+    added: Vec<&'a Change>,
+    removed: Vec<&'a Change>,
+}
+
+#[derive(Serialize)]
+struct ProfileSolution<'a> {
+    profile: &'a str,
+    #[serde(flatten)]
+    solution: &'a Solution,
+}
+
+// Descriptions of the PTS files span several lines.
+pub fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn yes_no(value: bool) -> String {
+    String::from(if value { "yes" } else { "no" })
+}
+
+// Write rows with aligned columns, the last one is not padded.
+fn table(to: &mut impl Write, header: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.iter().map(|name| name.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let last = row.len() - 1;
+        for (index, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if index == last {
+                writeln!(to, "{}", cell)?;
+            } else {
+                write!(to, "{:width$}  ", cell, width = width)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json(to: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *to, value)?;
+    writeln!(to)
+}
+
+fn ics_value(value: Option<bool>) -> &'static str {
+    match value {
+        Some(true) => "TRUE",
+        Some(false) => "FALSE",
+        None => "undefined",
+    }
+}
+
+fn causes(causes: &[Cause]) -> String {
+    causes
+        .iter()
+        .map(|cause| {
+            format!(
+                "{} {} -> {}",
+                cause.name,
+                ics_value(cause.old),
+                ics_value(cause.new)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn load<'pts>(pts: &'pts PTS, profile: &str) -> Result<Profile<'pts>> {
+    pts.profile(profile)
+        .with_context(|| format!("Profile '{}' not found", profile))
+}
+
+// Load the profile of a test, named by its first component.
+fn load_test_profile<'pts>(pts: &'pts PTS, test: &str) -> Result<Profile<'pts>> {
+    load(pts, test.split('/').next().unwrap_or(test))
+}
+
+fn find<'a>(testcases: &'a [EtsTestCase<'a>], test: &str) -> Result<&'a EtsTestCase<'a>> {
+    testcases
+        .iter()
+        .find(|testcase| testcase.name == test)
+        .with_context(|| format!("Test '{}' not found", test))
+}
+
+fn profiles(to: &mut impl Write, pts: &PTS, format: Format) -> Result<()> {
+    let profiles = pts.profiles().context("Failed to list profiles")?;
+    match format {
+        Format::Table => {
+            let rows = profiles
+                .into_iter()
+                .map(|name| vec![name])
+                .collect::<Vec<_>>();
+            table(to, &["Profile"], &rows)?
+        }
+        Format::Json => json(to, &profiles)?,
+    }
+    Ok(())
+}
+
+fn tests(to: &mut impl Write, testcases: &[EtsTestCase], format: Format) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            let rows = testcases
+                .iter()
+                .map(|testcase| {
+                    vec![
+                        yes_no(testcase.enabled),
+                        testcase.name.to_owned(),
+                        testcase.groups.join("/"),
+                        single_line(testcase.description),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            table(to, &["Enabled", "Test", "Group", "Description"], &rows)
+        }
+        Format::Json => json(to, &testcases.iter().map(Test::from).collect::<Vec<_>>()),
+    }
+}
+
+fn describe(to: &mut impl Write, testcase: &EtsTestCase, format: Format) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            let rows = [
+                ("Test", testcase.name.to_owned()),
+                ("Group", testcase.groups.join("/")),
+                ("Enabled", yes_no(testcase.enabled)),
+                ("Mapping", single_line(testcase.mapping)),
+                ("Description", testcase.description.trim().to_owned()),
+            ];
+            for (name, value) in rows {
+                writeln!(to, "{:12} {}", format!("{}:", name), value)?;
+            }
+            Ok(())
+        }
+        Format::Json => json(to, &Test::from(testcase)),
+    }
+}
+
+fn ics(to: &mut impl Write, profile: &Profile, format: Format) -> io::Result<()> {
+    let rows = profile
+        .pics()
+        .map(|row| Ics {
+            name: &row.name,
+            default: row.value,
+            mandatory: row.mandatory,
+            description: &row.description,
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Table => {
+            let rows = rows
+                .iter()
+                .map(|ics| {
+                    vec![
+                        ics.name.to_owned(),
+                        ics.default.to_string().to_uppercase(),
+                        yes_no(ics.mandatory),
+                        single_line(ics.description),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            table(to, &["ICS", "Default", "Mandatory", "Description"], &rows)
+        }
+        Format::Json => json(to, &rows),
+    }
+}
+
+fn pixit(to: &mut impl Write, profile: &Profile, format: Format) -> io::Result<()> {
+    let rows = profile
+        .pixit()
+        .map(|row| Ixit {
+            name: &row.name,
+            value_type: row.value_type.first().map(String::as_str).unwrap_or(""),
+            default: &row.value,
+            description: &row.description,
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Table => {
+            let rows = rows
+                .iter()
+                .map(|ixit| {
+                    vec![
+                        ixit.name.to_owned(),
+                        ixit.value_type.to_owned(),
+                        ixit.default.to_owned(),
+                        single_line(ixit.description),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            table(to, &["IXIT", "Type", "Default", "Description"], &rows)
+        }
+        Format::Json => json(to, &rows),
+    }
+}
+
+fn ics_diff(
+    to: &mut impl Write,
+    changes: &[(String, Vec<Change>)],
+    format: Format,
+) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            let rows = changes
+                .iter()
+                .flat_map(|(profile, changes)| {
+                    changes.iter().map(move |change| {
+                        vec![
+                            profile.clone(),
+                            String::from(if change.enabled { "+" } else { "-" }),
+                            change.test.clone(),
+                            causes(&change.causes),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                return writeln!(to, "No test enabled or disabled");
+            }
+            table(to, &["Profile", "", "Test", "ICS"], &rows)
+        }
+        Format::Json => json(
+            to,
+            &changes
+                .iter()
+                .map(|(profile, changes)| ProfileChanges {
+                    profile,
+                    added: changes.iter().filter(|change| change.enabled).collect(),
+                    removed: changes.iter().filter(|change| !change.enabled).collect(),
+                })
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+// Targets of the solver in the profile, with their mapping.
+fn solve_profile(profile: &Profile, selectors: &[&(String, Selector)]) -> Result<Solution> {
+    let testcases = profile.test_cases();
+    for (text, selector) in selectors {
+        if !testcases
+            .iter()
+            .any(|testcase| selector.matches(testcase.name))
+        {
+            bail!("No test matching '{}'", text);
+        }
+    }
+    let targets = testcases
+        .iter()
+        .filter(|testcase| {
+            selectors
+                .iter()
+                .any(|(_, selector)| selector.matches(testcase.name))
+        })
+        .map(|testcase| (testcase.name, testcase.mapping))
+        .collect::<Vec<_>>();
+
+    let item = |name: &str| match profile.ics(name) {
+        Some((value, IcsSource::Config)) => Item::Config(value),
+        Some((value, IcsSource::Default)) => {
+            if profile.pics().any(|row| row.name == name && row.mandatory) {
+                Item::Mandatory(value)
+            } else {
+                Item::Free(value)
+            }
+        }
+        None => Item::Undefined,
+    };
+    Ok(solver::solve(&targets, &item))
+}
+
+fn solve(to: &mut impl Write, solutions: &[(&str, Solution)], format: Format) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            let rows = solutions
+                .iter()
+                .flat_map(|(profile, solution)| {
+                    solution.assignments.iter().map(move |assignment| {
+                        vec![
+                            profile.to_string(),
+                            assignment.name.clone(),
+                            ics_value(Some(assignment.value)).to_owned(),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if rows.is_empty() {
+                writeln!(to, "No ICS to change")?;
+            } else {
+                table(to, &["Profile", "ICS", "Value"], &rows)?;
+            }
+
+            let rows = solutions
+                .iter()
+                .flat_map(|(_, solution)| {
+                    solution.unsatisfiable.iter().map(|unsatisfiable| {
+                        vec![unsatisfiable.test.clone(), unsatisfiable.reason.clone()]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !rows.is_empty() {
+                writeln!(to, "\nUnsatisfiable:")?;
+                table(to, &["Test", "Reason"], &rows)?;
+            }
+            Ok(())
+        }
+        Format::Json => json(
+            to,
+            &solutions
+                .iter()
+                .map(|(profile, solution)| ProfileSolution { profile, solution })
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+pub fn run(pts: &PTS, command: &Command) -> Result<()> {
+    let stdout = stdout();
+    let to = &mut stdout.lock();
+
+    match command {
+        Command::Profiles { output } => profiles(to, pts, output.format)?,
+        Command::Tests { profile, output } => {
+            let profile = load(pts, profile)?;
+            tests(to, &profile.test_cases(), output.format)?
+        }
+        Command::Describe { test, output } => {
+            let profile = load_test_profile(pts, test)?;
+            describe(to, find(&profile.test_cases(), test)?, output.format)?
+        }
+        Command::Explain { test, output } => {
+            let profile = load_test_profile(pts, test)?;
+            let json = matches!(output.format, Format::Json);
+            explain::write(
+                to,
+                find(&profile.test_cases(), test)?,
+                |name| profile.ics(name),
+                json,
+            )?
+        }
+        Command::Ics { profile, output } => ics(to, &load(pts, profile)?, output.format)?,
+        Command::Pixit { profile, output } => pixit(to, &load(pts, profile)?, output.format)?,
+        Command::IcsDiff {
+            old,
+            new,
+            profile,
+            output,
+        } => {
+            let old = Config::load(old)?;
+            let new = Config::load(new)?;
+            let profiles = if profile.is_empty() {
+                pts.profiles().context("Failed to list profiles")?
+            } else {
+                profile.clone()
+            };
+
+            let mut changes = Vec::new();
+            for name in profiles {
+                let changed = impact::changes(
+                    &load(pts, &name)?,
+                    &impact::ics(&old, &name),
+                    &impact::ics(&new, &name),
+                );
+                if !changed.is_empty() {
+                    changes.push((name, changed));
+                }
+            }
+            ics_diff(to, &changes, output.format)?
+        }
+        Command::Solve { tests, output } => {
+            let selectors = tests
+                .iter()
+                .map(|text| Ok((text.clone(), text.parse()?)))
+                .collect::<Result<Vec<(String, Selector)>>>()?;
+
+            let mut profiles: Vec<&str> = Vec::new();
+            for (_, selector) in selectors.iter() {
+                if !profiles.contains(&selector.profile()) {
+                    profiles.push(selector.profile());
+                }
+            }
+
+            let mut solutions = Vec::new();
+            for name in profiles {
+                let selectors = selectors
+                    .iter()
+                    .filter(|(_, selector)| selector.profile() == name)
+                    .collect::<Vec<_>>();
+                solutions.push((name, solve_profile(&load(pts, name)?, &selectors)?));
+            }
+            solve(to, &solutions, output.format)?
+        }
+        Command::Template { profiles } => {
+            let profiles = profiles
+                .iter()
+                .map(|name| load(pts, name))
+                .collect::<Result<Vec<_>>>()?;
+            let definitions = profiles
+                .iter()
+                .map(|profile| template::Definitions {
+                    name: profile.name(),
+                    pics: profile.pics().collect(),
+                    pixit: profile.pixit().collect(),
+                })
+                .collect::<Vec<_>>();
+            template::write(to, &definitions)?
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{describe, ics_diff, solve, table, tests, Format};
+    use crate::impact::{Cause, Change};
+    use crate::solver::{Assignment, Solution, Unsatisfiable};
+    use libpts::EtsTestCase;
+
+    fn testcase() -> EtsTestCase<'static> {
+        EtsTestCase {
+            name: "A2DP/SRC/AS/BV-01-I",
+            groups: vec!["SRC", "AS"],
+            description: "Verify that the SRC streams\n    audio.",
+            mapping: "TSPC_A2DP_2_1 AND TSPC_A2DP_2_2",
+            enabled: true,
+        }
+    }
+
+    fn output(write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>) -> String {
+        let mut output = Vec::new();
+        write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_table() {
+        let rows = vec![
+            vec![String::from("TSPX_psm"), String::from("0001")],
+            vec![String::from("TSPX_time_guard"), String::from("180000")],
+        ];
+        assert_eq!(
+            output(|to| table(to, &["IXIT", "Default"], &rows)),
+            "IXIT             Default\nTSPX_psm         0001\nTSPX_time_guard  180000\n"
+        );
+    }
+
+    #[test]
+    fn test_tests() {
+        assert_eq!(
+            output(|to| tests(to, &[testcase()], Format::Table)),
+            "Enabled  Test                 Group   Description\n\
+             yes      A2DP/SRC/AS/BV-01-I  SRC/AS  Verify that the SRC streams audio.\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&output(|to| tests(to, &[testcase()], Format::Json))).unwrap();
+        assert_eq!(json[0]["groups"], serde_json::json!(["SRC", "AS"]));
+        assert_eq!(json[0]["enabled"], true);
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            output(|to| describe(to, &testcase(), Format::Table)),
+            "Test:        A2DP/SRC/AS/BV-01-I\n\
+             Group:       SRC/AS\n\
+             Enabled:     yes\n\
+             Mapping:     TSPC_A2DP_2_1 AND TSPC_A2DP_2_2\n\
+             Description: Verify that the SRC streams\n    audio.\n"
+        );
+    }
+
+    #[test]
+    fn test_ics_diff() {
+        let changes = vec![(
+            String::from("HFP"),
+            vec![
+                Change {
+                    test: String::from("HFP/AG/SLC/BV-03-C"),
+                    enabled: true,
+                    causes: vec![Cause {
+                        name: String::from("TSPC_HFP_2_1"),
+                        old: Some(false),
+                        new: Some(true),
+                    }],
+                },
+                Change {
+                    test: String::from("HFP/AG/SLC/BV-04-C"),
+                    enabled: false,
+                    causes: vec![Cause {
+                        name: String::from("TSPC_HFP_2_2"),
+                        old: Some(true),
+                        new: None,
+                    }],
+                },
+            ],
+        )];
+
+        assert_eq!(
+            output(|to| ics_diff(to, &changes, Format::Table)),
+            "Profile     Test                ICS\n\
+             HFP      +  HFP/AG/SLC/BV-03-C  TSPC_HFP_2_1 FALSE -> TRUE\n\
+             HFP      -  HFP/AG/SLC/BV-04-C  TSPC_HFP_2_2 TRUE -> undefined\n"
+        );
+        assert_eq!(
+            output(|to| ics_diff(to, &[], Format::Table)),
+            "No test enabled or disabled\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&output(|to| ics_diff(to, &changes, Format::Json))).unwrap();
+        assert_eq!(json[0]["added"][0]["test"], "HFP/AG/SLC/BV-03-C");
+        assert_eq!(
+            json[0]["removed"][0]["causes"][0]["new"],
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_solve() {
+        let solution = Solution {
+            assignments: vec![Assignment {
+                name: String::from("TSPC_HFP_3_1"),
+                value: true,
+            }],
+            unsatisfiable: vec![Unsatisfiable {
+                test: String::from("HFP/AG/SLC/BV-03-C"),
+                reason: String::from("Conflicts with HFP/AG/SLC/BV-02-C"),
+            }],
+        };
+
+        assert_eq!(
+            output(|to| solve(to, &[("HFP", solution)], Format::Table)),
+            "Profile  ICS           Value\n\
+             HFP      TSPC_HFP_3_1  TRUE\n\
+             \n\
+             Unsatisfiable:\n\
+             Test                Reason\n\
+             HFP/AG/SLC/BV-03-C  Conflicts with HFP/AG/SLC/BV-02-C\n"
+        );
+    }
+}
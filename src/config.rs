@@ -0,0 +1,617 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::jsonc;
+use crate::launch_studio;
+
+/// Config of a run. It can extend other configs, and its ICS, IXIT and
+/// skips are either global or in sections named after a profile:
+///
+/// ```jsonc
+/// {
+///   "extends": ["../common.jsonc", "product.pts"],
+///   "ics": { "TSPC_ALL": true, "A2DP": { "TSPC_A2DP_1_1": true } },
+///   "ixit": { "default": {}, "A2DP": { "TSPX_bd_addr_iut": "${IUT_ADDRESS}" } },
+///   "skip": { "A2DP": ["A2DP/SRC/CC/BV-09-I"] }
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(from = "RawConfig")]
+pub struct Config {
+    /// Configs overlaid by this one, relative to its directory.
+    pub extends: Vec<String>,
+    pub ics: HashMap<String, bool>,
+    /// ICS applying to one profile only.
+    pub profile_ics: HashMap<String, HashMap<String, bool>>,
+    pub ixit: HashMap<String, HashMap<String, String>>,
+    pub skip: Option<Vec<String>>,
+    pub profile_skip: HashMap<String, Vec<String>>,
+    /// Overrides for the tests matching the key,
+    /// which is either a test name or a glob.
+    pub tests: HashMap<String, TestConfig>,
+    /// Values of `--ics` and `--ixit`, by profile or `default`,
+    /// applied to each test after all its other overrides.
+    pub cli: HashMap<String, TestConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TestConfig {
+    #[serde(default)]
+    pub ics: HashMap<String, bool>,
+    #[serde(default)]
+    pub ixit: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IcsEntry {
+    Value(bool),
+    Profile(HashMap<String, bool>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Skip {
+    Tests(Vec<String>),
+    Profiles(HashMap<String, Vec<String>>),
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    #[serde(default, alias = "include")]
+    extends: Vec<String>,
+    #[serde(default)]
+    ics: HashMap<String, IcsEntry>,
+    #[serde(default)]
+    ixit: HashMap<String, HashMap<String, String>>,
+    skip: Option<Skip>,
+    #[serde(default)]
+    tests: HashMap<String, TestConfig>,
+}
+
+impl From<RawConfig> for Config {
+    fn from(raw: RawConfig) -> Self {
+        let mut config = Config {
+            extends: raw.extends,
+            ixit: raw.ixit,
+            tests: raw.tests,
+            ..Default::default()
+        };
+        for (name, entry) in raw.ics {
+            match entry {
+                IcsEntry::Value(value) => {
+                    config.ics.insert(name, value);
+                }
+                IcsEntry::Profile(ics) => {
+                    config.profile_ics.insert(name, ics);
+                }
+            }
+        }
+        match raw.skip {
+            Some(Skip::Tests(tests)) => config.skip = Some(tests),
+            Some(Skip::Profiles(profiles)) => config.profile_skip = profiles,
+            None => {}
+        }
+        config
+    }
+}
+
+/// Value of `--ics NAME=BOOL`.
+#[derive(Debug)]
+pub struct IcsOverride {
+    pub name: String,
+    pub value: bool,
+}
+
+impl FromStr for IcsOverride {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (name, value) = value
+            .split_once('=')
+            .with_context(|| format!("Expected NAME=BOOL, got '{}'", value))?;
+        let value = match value.to_lowercase().as_str() {
+            "true" => true,
+            "false" => false,
+            _ => bail!(
+                "Invalid value '{}' for {}, expected TRUE or FALSE",
+                value,
+                name
+            ),
+        };
+        Ok(IcsOverride {
+            name: name.to_owned(),
+            value,
+        })
+    }
+}
+
+/// Value of `--ixit PROFILE.NAME=VALUE`, the profile
+/// is `default` for the IXIT of all the profiles.
+#[derive(Debug)]
+pub struct IxitOverride {
+    pub profile: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for IxitOverride {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let parse = || {
+            let (key, value) = value.split_once('=')?;
+            let (profile, name) = key.split_once('.')?;
+            Some(IxitOverride {
+                profile: profile.to_owned(),
+                name: name.to_owned(),
+                value: value.to_owned(),
+            })
+        };
+        parse().with_context(|| format!("Expected PROFILE.NAME=VALUE, got '{}'", value))
+    }
+}
+
+/// Replace the `${NAME}` of a value by the variable of the environment.
+fn substitute(value: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unterminated variable in '{}'", value))?;
+        let name = &rest[start + 2..start + end];
+        let variable =
+            env(name).with_context(|| format!("Environment variable {} is not set", name))?;
+        result.push_str(&rest[..start]);
+        result.push_str(&variable);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+impl Config {
+    /// Load a JSONC config with the configs it extends, or the ICS
+    /// of a Launch Studio `.pts` file with empty IXIT sections.
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::load_extended(path, &mut Vec::new())
+    }
+
+    // `stack` holds the configs being loaded, to detect cycles.
+    fn load_extended(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open config file {}", path.display()))?;
+        let file = BufReader::new(file);
+        if path.extension().is_some_and(|extension| extension == "pts") {
+            let ics = launch_studio::parse(file)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            let ixit = std::iter::once(String::from("default"))
+                .chain(ics.profiles)
+                .map(|profile| (profile, HashMap::new()))
+                .collect();
+            return Ok(Config {
+                ics: ics.ics,
+                ixit,
+                ..Default::default()
+            });
+        }
+
+        let mut config: Config = jsonc::from_reader(file)
+            .with_context(|| format!("Failed to parse config {}", path.display()))?;
+        config
+            .substitute(&|name| std::env::var(name).ok())
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+
+        let canonical = path.canonicalize()?;
+        if stack.contains(&canonical) {
+            bail!("Config {} extends itself", path.display());
+        }
+        stack.push(canonical);
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut extended: Option<Config> = None;
+        for base in std::mem::take(&mut config.extends) {
+            let base = Self::load_extended(&directory.join(base), stack)?;
+            extended = Some(match extended {
+                Some(mut extended) => {
+                    extended.merge(base);
+                    extended
+                }
+                None => base,
+            });
+        }
+        stack.pop();
+
+        Ok(match extended {
+            Some(mut extended) => {
+                extended.merge(config);
+                extended
+            }
+            None => config,
+        })
+    }
+
+    /// Load the configs, each one overlaying the previous ones.
+    pub fn load_all(paths: &[impl AsRef<Path>]) -> Result<Option<Self>> {
+        let mut config: Option<Config> = None;
+        for path in paths {
+            let overlay = Config::load(path.as_ref())?;
+            config = Some(match config {
+                Some(mut config) => {
+                    config.merge(overlay);
+                    config
+                }
+                None => overlay,
+            });
+        }
+        Ok(config)
+    }
+
+    fn substitute(&mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<()> {
+        for base in self.extends.iter_mut() {
+            *base = substitute(base, env)?;
+        }
+        let ixit = self
+            .ixit
+            .values_mut()
+            .chain(self.tests.values_mut().map(|overrides| &mut overrides.ixit));
+        for value in ixit.flat_map(|ixit| ixit.values_mut()) {
+            *value = substitute(value, env)?;
+        }
+        Ok(())
+    }
+
+    /// Overlay the values of another config: its ICS, IXIT and test
+    /// overrides replace the ones with the same name, skips are added.
+    pub fn merge(&mut self, overlay: Config) {
+        self.ics.extend(overlay.ics);
+        for (profile, ics) in overlay.profile_ics {
+            self.profile_ics.entry(profile).or_default().extend(ics);
+        }
+        for (profile, ixit) in overlay.ixit {
+            self.ixit.entry(profile).or_default().extend(ixit);
+        }
+        if let Some(skip) = overlay.skip {
+            self.skip.get_or_insert_with(Vec::new).extend(skip);
+        }
+        for (profile, skip) in overlay.profile_skip {
+            self.profile_skip.entry(profile).or_default().extend(skip);
+        }
+        for (key, overrides) in overlay.tests {
+            let test = self.tests.entry(key).or_default();
+            test.ics.extend(overrides.ics);
+            test.ixit.extend(overrides.ixit);
+        }
+    }
+
+    /// Apply the `--ics` and `--ixit` overrides, after all the files.
+    /// They replace the values of the profile sections, and the test
+    /// overrides through `test_overrides`. The IXIT of a profile takes
+    /// precedence over the `default` one whatever their order.
+    pub fn apply_overrides(&mut self, ics: &[IcsOverride], ixit: &[IxitOverride]) {
+        for ics in ics {
+            self.ics.insert(ics.name.clone(), ics.value);
+            for profile_ics in self.profile_ics.values_mut() {
+                if let Some(value) = profile_ics.get_mut(&ics.name) {
+                    *value = ics.value;
+                }
+            }
+            self.cli
+                .entry(String::from("default"))
+                .or_default()
+                .ics
+                .insert(ics.name.clone(), ics.value);
+        }
+        let (default, profiles): (Vec<_>, Vec<_>) =
+            ixit.iter().partition(|ixit| ixit.profile == "default");
+        for ixit in default.into_iter().chain(profiles) {
+            if ixit.profile == "default" {
+                for profile_ixit in self.ixit.values_mut() {
+                    if let Some(value) = profile_ixit.get_mut(&ixit.name) {
+                        *value = ixit.value.clone();
+                    }
+                }
+            }
+            self.ixit
+                .entry(ixit.profile.clone())
+                .or_default()
+                .insert(ixit.name.clone(), ixit.value.clone());
+            self.cli
+                .entry(ixit.profile.clone())
+                .or_default()
+                .ixit
+                .insert(ixit.name.clone(), ixit.value.clone());
+        }
+    }
+
+    /// All the tests to skip, global or in a profile section.
+    pub fn skipped(&self) -> impl Iterator<Item = &String> {
+        self.skip
+            .iter()
+            .flatten()
+            .chain(self.profile_skip.values().flatten())
+    }
+
+    /// Overrides applying to the test, in increasing order of precedence:
+    /// globs first, shorter ones before longer ones, then the exact name,
+    /// and finally the `--ics` and `--ixit` values.
+    pub fn test_overrides(&self, test: &str) -> Result<Vec<&TestConfig>> {
+        let mut globs = Vec::new();
+        for (key, overrides) in self.tests.iter() {
+            if key != test && key.contains(['*', '?', '[']) {
+                let pattern = Pattern::new(key)
+                    .with_context(|| format!("Invalid glob '{}' in config tests", key))?;
+                if pattern.matches(test) {
+                    globs.push((key, overrides));
+                }
+            }
+        }
+        globs.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(a.cmp(b)));
+
+        Ok(globs
+            .into_iter()
+            .map(|(_, overrides)| overrides)
+            .chain(self.tests.get(test))
+            .chain(self.cli.get("default"))
+            .chain(
+                test.split('/')
+                    .next()
+                    .filter(|&profile| profile != "default")
+                    .and_then(|profile| self.cli.get(profile)),
+            )
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{substitute, Config, IcsOverride, IxitOverride, TestConfig};
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn config(tests: &[(&str, &str)]) -> Config {
+        Config {
+            tests: tests
+                .iter()
+                .map(|(key, psm)| {
+                    let mut overrides = TestConfig::default();
+                    overrides
+                        .ixit
+                        .insert(String::from("TSPX_psm"), psm.to_string());
+                    (key.to_string(), overrides)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn psms(config: &Config, test: &str) -> Vec<String> {
+        config
+            .test_overrides(test)
+            .unwrap()
+            .into_iter()
+            .map(|overrides| overrides.ixit["TSPX_psm"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_overrides_order() {
+        let config = config(&[
+            ("L2CAP/COS/CED/BV-01-C", "exact"),
+            ("L2CAP/COS/*", "short"),
+            ("L2CAP/COS/CED/*", "long"),
+            ("L2CAP/LE/*", "other"),
+        ]);
+
+        assert_eq!(
+            psms(&config, "L2CAP/COS/CED/BV-01-C"),
+            ["short", "long", "exact"]
+        );
+        assert_eq!(psms(&config, "L2CAP/COS/CED/BV-03-C"), ["short", "long"]);
+        assert!(psms(&config, "L2CAP/CLS/UCD/BV-01-C").is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut config: Config = serde_json::from_str(
+            r#"{
+                "ics": { "TSPC_A2DP_1_1": true, "TSPC_A2DP_2_1": true },
+                "ixit": { "default": { "TSPX_bd_addr_iut": "000000000000" }, "A2DP": {} },
+                "skip": ["A2DP/SRC/CC/BV-09-I"]
+            }"#,
+        )
+        .unwrap();
+        let overlay: Config = serde_json::from_str(
+            r#"{
+                "ics": { "TSPC_A2DP_2_1": false },
+                "ixit": { "A2DP": { "TSPX_no_avrcp": "TRUE" } },
+                "skip": ["A2DP/SRC/AS/BV-01-I"]
+            }"#,
+        )
+        .unwrap();
+
+        config.merge(overlay);
+        assert!(config.ics["TSPC_A2DP_1_1"]);
+        assert!(!config.ics["TSPC_A2DP_2_1"]);
+        assert_eq!(config.ixit["default"].len(), 1);
+        assert_eq!(config.ixit["A2DP"]["TSPX_no_avrcp"], "TRUE");
+        assert_eq!(
+            config.skip.unwrap(),
+            ["A2DP/SRC/CC/BV-09-I", "A2DP/SRC/AS/BV-01-I"]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "ics": {},
+                "ixit": {},
+                "tests": {
+                    "L2CAP/COS/CED/BV-01-C": { "ixit": { "TSPX_psm": "1001" } }
+                }
+            }"#,
+        )
+        .unwrap();
+        let overrides = config.test_overrides("L2CAP/COS/CED/BV-01-C").unwrap();
+        assert_eq!(overrides.len(), 1);
+        assert!(overrides[0].ics.is_empty());
+        assert_eq!(overrides[0].ixit["TSPX_psm"], "1001");
+    }
+
+    #[test]
+    fn test_profile_sections() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "ics": { "TSPC_ALL": true, "A2DP": { "TSPC_A2DP_1_1": false } },
+                "skip": { "A2DP": ["A2DP/SRC/CC/BV-09-I"], "AVRCP": ["AVRCP/TG/CEC/BV-01-I"] }
+            }"#,
+        )
+        .unwrap();
+        assert!(config.ics["TSPC_ALL"]);
+        assert!(!config.profile_ics["A2DP"]["TSPC_A2DP_1_1"]);
+        let mut skipped = config.skipped().collect::<Vec<_>>();
+        skipped.sort();
+        assert_eq!(skipped, ["A2DP/SRC/CC/BV-09-I", "AVRCP/TG/CEC/BV-01-I"]);
+    }
+
+    #[test]
+    fn test_extends() {
+        let directory = std::env::temp_dir().join(format!("pts-bot-config-{}", std::process::id()));
+        fs::create_dir_all(directory.join("products")).unwrap();
+        fs::write(
+            directory.join("common.jsonc"),
+            r#"{
+                "ics": { "TSPC_A2DP_1_1": true, "TSPC_A2DP_2_1": true },
+                "ixit": { "default": { "TSPX_delete_ltk": "TRUE" }, "A2DP": { "TSPX_no_avrcp": "TRUE" } },
+                "tests": { "A2DP/*": { "ics": { "TSPC_A2DP_3_1": true } } },
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            directory.join("products/phone.jsonc"),
+            r#"{
+                "extends": ["../common.jsonc"],
+                "ics": { "A2DP": { "TSPC_A2DP_2_1": false } },
+                "ixit": { "A2DP": { "TSPX_bd_addr_iut": "${CARGO_MANIFEST_DIR}" } },
+                "tests": { "A2DP/*": { "ixit": { "TSPX_time_guard": "300000" } } }
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            directory.join("loop.jsonc"),
+            r#"{ "include": ["loop.jsonc"] }"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&directory.join("products/phone.jsonc"));
+        let looping = Config::load(&directory.join("loop.jsonc"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        let config = config.unwrap();
+        assert!(config.ics["TSPC_A2DP_2_1"]);
+        assert!(!config.profile_ics["A2DP"]["TSPC_A2DP_2_1"]);
+        assert_eq!(config.ixit["default"]["TSPX_delete_ltk"], "TRUE");
+        assert_eq!(config.ixit["A2DP"]["TSPX_no_avrcp"], "TRUE");
+        // Set by cargo when running the tests
+        assert_eq!(
+            config.ixit["A2DP"]["TSPX_bd_addr_iut"],
+            env!("CARGO_MANIFEST_DIR")
+        );
+        assert_eq!(config.tests["A2DP/*"].ics.len(), 1);
+        assert_eq!(config.tests["A2DP/*"].ixit.len(), 1);
+
+        assert!(format!("{:#}", looping.unwrap_err()).contains("extends itself"));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let env = |name: &str| (name == "IUT").then(|| String::from("DA4C10DE17E0"));
+        assert_eq!(substitute("${IUT}", &env).unwrap(), "DA4C10DE17E0");
+        assert_eq!(
+            substitute("0x${IUT}/$x", &env).unwrap(),
+            "0xDA4C10DE17E0/$x"
+        );
+        assert!(substitute("${PTS}", &env).is_err());
+        assert!(substitute("${IUT", &env).is_err());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut config = Config::default();
+        config.apply_overrides(
+            &["TSPC_A2DP_1_1=TRUE".parse::<IcsOverride>().unwrap()],
+            &["A2DP.TSPX_psm=0019".parse::<IxitOverride>().unwrap()],
+        );
+        assert!(config.ics["TSPC_A2DP_1_1"]);
+        assert_eq!(config.ixit["A2DP"]["TSPX_psm"], "0019");
+
+        // The command line beats the profile sections and the test overrides
+        let mut config: Config = serde_json::from_str(
+            r#"{
+                "ics": { "TSPC_A2DP_1_1": false, "A2DP": { "TSPC_A2DP_1_1": false } },
+                "ixit": {
+                    "default": { "TSPX_delete_ltk": "FALSE" },
+                    "A2DP": { "TSPX_delete_ltk": "FALSE", "TSPX_psm": "0001" }
+                },
+                "tests": {
+                    "A2DP/*": {
+                        "ics": { "TSPC_A2DP_1_1": false },
+                        "ixit": { "TSPX_delete_ltk": "FALSE", "TSPX_psm": "0003" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        config.apply_overrides(
+            &["TSPC_A2DP_1_1=TRUE".parse::<IcsOverride>().unwrap()],
+            &[
+                "A2DP.TSPX_psm=0019".parse::<IxitOverride>().unwrap(),
+                "default.TSPX_psm=0017".parse::<IxitOverride>().unwrap(),
+                "default.TSPX_delete_ltk=TRUE"
+                    .parse::<IxitOverride>()
+                    .unwrap(),
+            ],
+        );
+        assert!(config.ics["TSPC_A2DP_1_1"]);
+        assert!(config.profile_ics["A2DP"]["TSPC_A2DP_1_1"]);
+        assert_eq!(config.ixit["A2DP"]["TSPX_delete_ltk"], "TRUE");
+        assert_eq!(config.ixit["A2DP"]["TSPX_psm"], "0019");
+
+        // Last override wins
+        let mut ics = HashMap::new();
+        let mut ixit = HashMap::new();
+        for overrides in config.test_overrides("A2DP/SRC/AS/BV-01-I").unwrap() {
+            ics.extend(overrides.ics.clone());
+            ixit.extend(overrides.ixit.clone());
+        }
+        assert!(ics["TSPC_A2DP_1_1"]);
+        assert_eq!(ixit["TSPX_delete_ltk"], "TRUE");
+        assert_eq!(ixit["TSPX_psm"], "0019");
+
+        assert!("TSPC_A2DP_1_1".parse::<IcsOverride>().is_err());
+        assert!("TSPC_A2DP_1_1=yes".parse::<IcsOverride>().is_err());
+        assert!("TSPX_psm=0019".parse::<IxitOverride>().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Write};
+
+use libpts::{EtsTestCase, Expression, IcsSource};
+use serde_json::{json, Value};
+
+/// Evaluated node of the mapping expression of a test.
+struct Node<'a> {
+    expression: &'a Expression,
+    value: Option<bool>,
+    /// The node is one of the causes of the test not being enabled.
+    blocking: bool,
+    source: Option<IcsSource>,
+    operands: Vec<Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    fn new(
+        expression: &'a Expression,
+        ics: &dyn Fn(&str) -> Option<(bool, IcsSource)>,
+        blocking: &dyn Fn(Option<bool>) -> bool,
+    ) -> Self {
+        let value = expression.evaluate(&|name| ics(name).map(|(value, _)| value));
+        let blocking = blocking(value);
+
+        let operands = match expression {
+            // The operands with the same value as an AND or an OR
+            // are the ones deciding its value.
+            Expression::And(operands) | Expression::Or(operands) => operands
+                .iter()
+                .map(|operand| Node::new(operand, ics, &|operand| blocking && operand == value))
+                .collect(),
+            Expression::Not(operand) => vec![Node::new(operand, ics, &|_| blocking)],
+            Expression::Item(_) | Expression::Const(_) => Vec::new(),
+        };
+
+        let source = match expression {
+            Expression::Item(name) => ics(name).map(|(_, source)| source),
+            _ => None,
+        };
+
+        Node {
+            expression,
+            value,
+            blocking,
+            source,
+            operands,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.expression {
+            Expression::And(_) => String::from("AND"),
+            Expression::Or(_) => String::from("OR"),
+            Expression::Not(_) => String::from("NOT"),
+            Expression::Item(name) => name.clone(),
+            Expression::Const(value) => value.to_string().to_uppercase(),
+        }
+    }
+
+    fn write(&self, to: &mut impl Write, depth: usize) -> io::Result<()> {
+        write!(
+            to,
+            "{:indent$}{} {} = {}",
+            "",
+            if self.blocking { '✘' } else { ' ' },
+            self.label(),
+            value(self.value),
+            indent = depth * 2
+        )?;
+        match (self.expression, self.source) {
+            (Expression::Item(_), Some(source)) => writeln!(to, " ({})", source_name(source))?,
+            (Expression::Item(_), None) => writeln!(to, " (not in PICSX)")?,
+            _ => writeln!(to)?,
+        }
+        for operand in self.operands.iter() {
+            operand.write(to, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        let mut node = json!({
+            "expression": self.label(),
+            "value": self.value,
+            "blocking": self.blocking,
+        });
+        if let Expression::Item(_) = self.expression {
+            node["source"] = json!(self.source.map(source_name));
+        } else if !self.operands.is_empty() {
+            node["operands"] = self.operands.iter().map(Node::to_json).collect();
+        }
+        node
+    }
+}
+
+fn value(value: Option<bool>) -> &'static str {
+    match value {
+        Some(true) => "TRUE",
+        Some(false) => "FALSE",
+        None => "UNRESOLVED",
+    }
+}
+
+fn source_name(source: IcsSource) -> &'static str {
+    match source {
+        IcsSource::Config => "config",
+        IcsSource::Default => "default",
+    }
+}
+
+/// Write the evaluation of the mapping of a test, with the value of
+/// each ICS item and where it comes from.
+pub fn write(
+    to: &mut impl Write,
+    testcase: &EtsTestCase,
+    ics: impl Fn(&str) -> Option<(bool, IcsSource)>,
+    json: bool,
+) -> io::Result<()> {
+    let expression = Expression::parse(testcase.mapping);
+    let root = expression
+        .as_ref()
+        .ok()
+        .map(|expression| Node::new(expression, &ics, &|value| value != Some(true)));
+    let unresolved = expression
+        .as_ref()
+        .map(|expression| {
+            expression
+                .items()
+                .into_iter()
+                .filter(|name| ics(name).is_none())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    // Like when the tests are listed, an unresolved item
+    // disables the test even when the mapping is true.
+    let value = root.as_ref().and_then(|root| root.value);
+    let enabled = match value {
+        Some(true) if !unresolved.is_empty() => Some(false),
+        value => value,
+    };
+
+    if json {
+        let mut explanation = json!({
+            "test": testcase.name,
+            "mapping": testcase.mapping,
+            "enabled": enabled,
+            "unresolved": unresolved,
+        });
+        match (&root, &expression) {
+            (Some(root), _) => explanation["expression"] = root.to_json(),
+            (None, Err(e)) => explanation["error"] = json!(e.to_string()),
+            _ => {}
+        }
+        serde_json::to_writer_pretty(&mut *to, &explanation)?;
+        return writeln!(to);
+    }
+
+    writeln!(to, "Test:    {}", testcase.name)?;
+    writeln!(
+        to,
+        "Enabled: {}",
+        match (value, enabled) {
+            (_, Some(true)) => "yes",
+            (Some(true), Some(false)) => "no, the mapping has unresolved ICS",
+            (_, Some(false)) => "no",
+            (_, None) => "no, the mapping could not be evaluated",
+        }
+    )?;
+    writeln!(to, "Mapping: {}", testcase.mapping.trim())?;
+    writeln!(to)?;
+
+    match (&root, &expression) {
+        (Some(root), _) => root.write(to, 0)?,
+        (None, Err(e)) => writeln!(to, "{}", e)?,
+        _ => {}
+    }
+
+    if !unresolved.is_empty() {
+        writeln!(to, "\nUnresolved ICS: {}", unresolved.join(", "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::write;
+    use libpts::{EtsTestCase, IcsSource};
+
+    fn explain(mapping: &str, json: bool) -> String {
+        let testcase = EtsTestCase {
+            name: "A2DP/SRC/AS/BV-01-I",
+            groups: vec!["SRC", "AS"],
+            description: "",
+            mapping,
+            enabled: false,
+        };
+        let ics = |name: &str| match name {
+            "TSPC_A2DP_1_1" => Some((true, IcsSource::Config)),
+            "TSPC_A2DP_2_1" => Some((false, IcsSource::Default)),
+            "TSPC_A2DP_2_2" => Some((false, IcsSource::Config)),
+            "TSPC_A2DP_2_3" => Some((true, IcsSource::Default)),
+            _ => None,
+        };
+
+        let mut output = Vec::new();
+        write(&mut output, &testcase, ics, json).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert_eq!(
+            explain(
+                "TSPC_A2DP_1_1 AND (TSPC_A2DP_2_1 OR TSPC_A2DP_2_2) AND TSPC_A2DP_2_3",
+                false
+            ),
+            "Test:    A2DP/SRC/AS/BV-01-I
+Enabled: no
+Mapping: TSPC_A2DP_1_1 AND (TSPC_A2DP_2_1 OR TSPC_A2DP_2_2) AND TSPC_A2DP_2_3
+
+✘ AND = FALSE
+    TSPC_A2DP_1_1 = TRUE (config)
+  ✘ OR = FALSE
+    ✘ TSPC_A2DP_2_1 = FALSE (default)
+    ✘ TSPC_A2DP_2_2 = FALSE (config)
+    TSPC_A2DP_2_3 = TRUE (default)
+"
+        );
+    }
+
+    #[test]
+    fn test_unresolved() {
+        let output = explain("TSPC_A2DP_1_1 AND TSPC_A2DP_9_9", false);
+        assert!(output.contains("Enabled: no, the mapping could not be evaluated"));
+        assert!(output.contains("  ✘ TSPC_A2DP_9_9 = UNRESOLVED (not in PICSX)\n"));
+        assert!(output.ends_with("\nUnresolved ICS: TSPC_A2DP_9_9\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&explain("TSPC_A2DP_1_1 AND TSPC_A2DP_9_9", true)).unwrap();
+        assert_eq!(json["enabled"], serde_json::Value::Null);
+        assert_eq!(json["unresolved"], serde_json::json!(["TSPC_A2DP_9_9"]));
+        assert_eq!(json["expression"]["operands"][0]["blocking"], false);
+        assert_eq!(
+            json["expression"]["operands"][1]["source"],
+            serde_json::Value::Null
+        );
+
+        let output = explain("TSPC_A2DP_1_1 OR TSPC_A2DP_9_9", false);
+        assert!(output.contains("Enabled: no, the mapping has unresolved ICS"));
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fault injection on the HCI link, configured with `--hci-fault`:
+//!
+//! ```text
+//! ACTION:MATCH[,MATCH...]
+//! ACTION = drop | delay=MS | duplicate | corrupt
+//! MATCH  = type=command|acl|sco|event|iso | opcode=N | event=N | acl=HANDLE
+//! ```
+//!
+//! A packet matching all the conditions of a rule gets its fault,
+//! the first matching rule applies.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error, Result};
+use async_channel::Sender;
+use async_io::Timer;
+use futures_lite::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libpts::h4::{Direction, PacketType, Reassembler};
+use libpts::packet::Packet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Drop,
+    /// Hold the packet, and the ones after it, for this duration.
+    Delay(Duration),
+    Duplicate,
+    /// Invert the last byte of the parameters or data,
+    /// the packets without any do not match.
+    Corrupt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Match {
+    Type(PacketType),
+    /// Command with this opcode.
+    Opcode(u16),
+    /// Event with this code.
+    Event(u8),
+    /// ACL packet of this connection handle.
+    Handle(u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub action: Action,
+    pub matches: Vec<Match>,
+}
+
+/// Fault injected on a packet.
+#[derive(Debug)]
+pub struct Fault {
+    pub direction: Direction,
+    pub action: Action,
+    pub packet: Packet,
+}
+
+// Decimal or 0x prefixed hexadecimal number.
+fn number<T: FromStr + TryFrom<u32>>(value: &str) -> Result<T> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+    number
+        .and_then(|number| T::try_from(number).ok())
+        .with_context(|| format!("Invalid number '{}'", value))
+}
+
+impl FromStr for Match {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Ok(match value.split_once('=') {
+            Some(("type", packet_type)) => Match::Type(match packet_type {
+                "command" => PacketType::Command,
+                "acl" => PacketType::Acl,
+                "sco" => PacketType::Sco,
+                "event" => PacketType::Event,
+                "iso" => PacketType::Iso,
+                _ => bail!(
+                    "Unknown packet type '{}', expected command, acl, sco, event or iso",
+                    packet_type
+                ),
+            }),
+            Some(("opcode", opcode)) => Match::Opcode(number(opcode)?),
+            Some(("event", code)) => Match::Event(number(code)?),
+            Some(("acl", handle)) => Match::Handle(number(handle)?),
+            _ => bail!(
+                "Invalid match '{}', expected type=TYPE, opcode=N, event=N or acl=HANDLE",
+                value
+            ),
+        })
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (action, matches) = value
+            .split_once(':')
+            .with_context(|| format!("Expected ACTION:MATCH, got '{}'", value))?;
+        let action = match action.split_once('=') {
+            None if action == "drop" => Action::Drop,
+            None if action == "duplicate" => Action::Duplicate,
+            None if action == "corrupt" => Action::Corrupt,
+            Some(("delay", ms)) => {
+                Action::Delay(Duration::from_millis(ms.parse().with_context(|| {
+                    format!("Invalid delay '{}', expected milliseconds", ms)
+                })?))
+            }
+            _ => bail!(
+                "Unknown action '{}', expected drop, delay=MS, duplicate or corrupt",
+                action
+            ),
+        };
+        let matches = matches
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Rule { action, matches })
+    }
+}
+
+impl Match {
+    fn matches(&self, packet: &[u8]) -> bool {
+        let packet_type = packet.first().copied().and_then(PacketType::from_u8);
+        let u16_at = |offset| {
+            packet
+                .get(offset..offset + 2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        };
+        match *self {
+            Match::Type(expected) => packet_type == Some(expected),
+            Match::Opcode(opcode) => {
+                packet_type == Some(PacketType::Command) && u16_at(1) == Some(opcode)
+            }
+            Match::Event(code) => {
+                packet_type == Some(PacketType::Event) && packet.get(1) == Some(&code)
+            }
+            Match::Handle(handle) => {
+                packet_type == Some(PacketType::Acl)
+                    && u16_at(1).map(|header| header & 0x0fff) == Some(handle)
+            }
+        }
+    }
+}
+
+impl Rule {
+    fn applies(&self, packet: &[u8]) -> bool {
+        self.matches.iter().all(|m| m.matches(packet))
+            && (self.action != Action::Corrupt || packet.len() > header_length(packet))
+    }
+}
+
+// Length of the H4 type and HCI header.
+fn header_length(packet: &[u8]) -> usize {
+    packet
+        .first()
+        .copied()
+        .and_then(PacketType::from_u8)
+        .map_or(packet.len(), |packet_type| 1 + packet_type.header_length())
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.action {
+            Action::Drop => write!(f, "Dropped {}", self.packet),
+            Action::Delay(delay) => {
+                write!(f, "Delayed by {}ms {}", delay.as_millis(), self.packet)
+            }
+            Action::Duplicate => write!(f, "Duplicated {}", self.packet),
+            Action::Corrupt => write!(f, "Corrupted {}", self.packet),
+        }
+    }
+}
+
+/// Copy the H4 packets from `reader` to `writer`, injecting the faults
+/// of the first rule matching each of them. The faults are reported
+/// to `faults`.
+pub async fn copy(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    direction: Direction,
+    rules: &[Rule],
+    faults: Sender<Fault>,
+) -> io::Result<()> {
+    let mut reassembler = Reassembler::default();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        for mut packet in reassembler.push(&buffer[..read]) {
+            let action = rules
+                .iter()
+                .find(|rule| rule.applies(&packet))
+                .map(|rule| rule.action);
+            if let Some(action) = action {
+                let _ = faults.try_send(Fault {
+                    direction,
+                    action,
+                    packet: Packet::decode(&packet),
+                });
+            }
+            match action {
+                None => writer.write_all(&packet).await?,
+                Some(Action::Drop) => {}
+                Some(Action::Delay(delay)) => {
+                    writer.flush().await?;
+                    Timer::after(delay).await;
+                    writer.write_all(&packet).await?;
+                }
+                Some(Action::Duplicate) => {
+                    writer.write_all(&packet).await?;
+                    writer.write_all(&packet).await?;
+                }
+                Some(Action::Corrupt) => {
+                    // Packets without parameters or data do not match
+                    *packet.last_mut().unwrap() ^= 0xff;
+                    writer.write_all(&packet).await?;
+                }
+            }
+        }
+        writer.flush().await?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy, Action, Match, Rule};
+    use async_io::block_on;
+    use libpts::h4::{Direction, PacketType};
+    use std::time::Duration;
+
+    const RESET: [u8; 4] = [0x01, 0x03, 0x0c, 0x00];
+    const WRITE_SCAN_ENABLE: [u8; 5] = [0x01, 0x1a, 0x0c, 0x01, 0x03];
+    const ACL: [u8; 9] = [0x02, 0x40, 0x20, 0x04, 0x00, 0x00, 0x00, 0x40, 0x00];
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "delay=200:type=event,event=0x0e".parse::<Rule>().unwrap(),
+            Rule {
+                action: Action::Delay(Duration::from_millis(200)),
+                matches: vec![Match::Type(PacketType::Event), Match::Event(0x0e)],
+            }
+        );
+        assert_eq!(
+            "drop:opcode=3075".parse::<Rule>().unwrap(),
+            Rule {
+                action: Action::Drop,
+                matches: vec![Match::Opcode(0x0c03)],
+            }
+        );
+        assert!("drop".parse::<Rule>().is_err());
+        assert!("reorder:type=acl".parse::<Rule>().is_err());
+        assert!("drop:type=hci".parse::<Rule>().is_err());
+        assert!("drop:event=0x1ff".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_copy() {
+        let run = |rule: &str| {
+            let rules = [rule.parse::<Rule>().unwrap()];
+            let input = [&RESET[..], &WRITE_SCAN_ENABLE[..], &ACL[..]].concat();
+            let (faults_tx, faults) = async_channel::unbounded();
+            let mut output = Vec::new();
+            block_on(copy(
+                &input[..],
+                &mut output,
+                Direction::Sent,
+                &rules,
+                faults_tx,
+            ))
+            .unwrap();
+            let faults: Vec<_> = std::iter::from_fn(|| faults.try_recv().ok())
+                .map(|fault| fault.to_string())
+                .collect();
+            (output, faults)
+        };
+
+        assert_eq!(
+            run("drop:opcode=0x0c03"),
+            (
+                [&WRITE_SCAN_ENABLE[..], &ACL[..]].concat(),
+                vec![String::from("Dropped Reset (0x0c03)")]
+            )
+        );
+        assert_eq!(
+            run("duplicate:acl=0x40").0,
+            [&RESET[..], &WRITE_SCAN_ENABLE[..], &ACL[..], &ACL[..]].concat()
+        );
+        // Reset has no parameters to corrupt
+        assert_eq!(
+            run("corrupt:type=command"),
+            (
+                [&RESET[..], &[0x01, 0x1a, 0x0c, 0x01, 0xfc][..], &ACL[..]].concat(),
+                vec![String::from("Corrupted Write Scan Enable (0x0c1a)")]
+            )
+        );
+        assert_eq!(
+            run("delay=1:type=acl").1,
+            [String::from(
+                "Delayed by 1ms ACL handle=0x0040 length=4 L2CAP cid=0x0040 length=0"
+            )]
+        );
+    }
+}
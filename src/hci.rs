@@ -0,0 +1,507 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::net::TcpStream;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{bail, Context, Error, Result};
+use async_io::Async;
+use blocking::Unblock;
+use futures_lite::io::{self, AsyncRead, AsyncWrite};
+use libpts::controller::Controller;
+use libpts::BdAddr;
+use nix::sys::signal::{self, Signal};
+use nix::sys::termios::{self, BaudRate, ControlFlags, SetArg};
+use nix::unistd::Pid;
+
+/// Transport carrying the H4 packets between PTS and the controller
+/// of the IUT, selected with `--hci`:
+/// - `PORT` or `tcp://HOST:PORT`,
+/// - `unix://PATH`, a Unix domain socket,
+/// - `serial://DEVICE?baud=RATE&flow=rtscts`, a serial device,
+/// - `cmd:COMMAND`, a shell command with H4 on its stdin and stdout,
+/// - `fake` or `fake:ADDRESS`, an in-process controller which only
+///   answers the initialization of the PTS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transport {
+    Tcp {
+        host: String,
+        port: u16,
+    },
+    Unix(PathBuf),
+    Serial {
+        device: PathBuf,
+        baud: u32,
+        flow_control: bool,
+    },
+    Command(String),
+    Fake(BdAddr),
+}
+
+pub type Reader = Box<dyn AsyncRead + Unpin + Send>;
+pub type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Output of a `cmd:` transport. The command, with the processes
+/// it started, is killed when the output is dropped, so that it
+/// does not outlive the test even if it ignores the end of its input.
+struct CommandOutput {
+    stdout: Unblock<ChildStdout>,
+    child: Child,
+}
+
+impl AsyncRead for CommandOutput {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl Drop for CommandOutput {
+    fn drop(&mut self) {
+        // The command leads its own process group.
+        let _ = signal::killpg(Pid::from_raw(self.child.id() as i32), Signal::SIGKILL);
+        let _ = self.child.wait();
+    }
+}
+
+const DEFAULT_BAUD: u32 = 115200;
+
+const DEFAULT_FAKE_ADDRESS: BdAddr = BdAddr::new([0x00, 0x1b, 0xdc, 0x00, 0x00, 0x01]);
+
+impl FromStr for Transport {
+    type Err = Error;
+
+    fn from_str(hci: &str) -> Result<Self> {
+        if let Ok(port) = hci.parse() {
+            return Ok(Transport::Tcp {
+                host: String::from("localhost"),
+                port,
+            });
+        }
+
+        if let Some(address) = hci.strip_prefix("tcp://") {
+            let (host, port) = address
+                .rsplit_once(':')
+                .with_context(|| format!("Expected tcp://HOST:PORT, got '{}'", hci))?;
+            let port = port
+                .parse()
+                .with_context(|| format!("Invalid port in '{}'", hci))?;
+            Ok(Transport::Tcp {
+                host: host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_owned(),
+                port,
+            })
+        } else if let Some(path) = hci.strip_prefix("unix://") {
+            Ok(Transport::Unix(PathBuf::from(path)))
+        } else if let Some(serial) = hci.strip_prefix("serial://") {
+            let (device, parameters) = serial.split_once('?').unwrap_or((serial, ""));
+            let mut baud = DEFAULT_BAUD;
+            let mut flow_control = false;
+            for parameter in parameters.split('&').filter(|p| !p.is_empty()) {
+                match parameter.split_once('=') {
+                    Some(("baud", value)) => {
+                        baud = value
+                            .parse()
+                            .ok()
+                            .filter(|baud| baud_rate(*baud).is_some())
+                            .with_context(|| format!("Unsupported baud rate '{}'", value))?
+                    }
+                    Some(("flow", "rtscts")) => flow_control = true,
+                    Some(("flow", "none")) => flow_control = false,
+                    _ => bail!(
+                        "Unknown serial parameter '{}', expected baud=RATE or flow=rtscts|none",
+                        parameter
+                    ),
+                }
+            }
+            Ok(Transport::Serial {
+                device: PathBuf::from(device),
+                baud,
+                flow_control,
+            })
+        } else if let Some(command) = hci.strip_prefix("cmd:") {
+            Ok(Transport::Command(command.to_owned()))
+        } else if hci == "fake" {
+            Ok(Transport::Fake(DEFAULT_FAKE_ADDRESS))
+        } else if let Some(address) = hci.strip_prefix("fake:") {
+            Ok(Transport::Fake(address.parse().with_context(|| {
+                format!("Invalid address of the fake controller '{}'", address)
+            })?))
+        } else {
+            bail!(
+                "Invalid HCI transport '{}', expected PORT, tcp://HOST:PORT, \
+                 unix://PATH, serial://DEVICE, cmd:COMMAND or fake",
+                hci
+            )
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transport::Tcp { host, port } => write!(f, "tcp://{}:{}", host, port),
+            Transport::Unix(path) => write!(f, "unix://{}", path.display()),
+            Transport::Serial {
+                device,
+                baud,
+                flow_control,
+            } => write!(
+                f,
+                "serial://{}?baud={}&flow={}",
+                device.display(),
+                baud,
+                if *flow_control { "rtscts" } else { "none" }
+            ),
+            Transport::Command(command) => write!(f, "cmd:{}", command),
+            Transport::Fake(address) => write!(f, "fake:{}", address),
+        }
+    }
+}
+
+fn baud_rate(baud: u32) -> Option<BaudRate> {
+    Some(match baud {
+        9600 => BaudRate::B9600,
+        19200 => BaudRate::B19200,
+        38400 => BaudRate::B38400,
+        57600 => BaudRate::B57600,
+        115200 => BaudRate::B115200,
+        230400 => BaudRate::B230400,
+        460800 => BaudRate::B460800,
+        921600 => BaudRate::B921600,
+        1000000 => BaudRate::B1000000,
+        1500000 => BaudRate::B1500000,
+        2000000 => BaudRate::B2000000,
+        3000000 => BaudRate::B3000000,
+        4000000 => BaudRate::B4000000,
+        _ => return None,
+    })
+}
+
+fn open_serial(device: &Path, baud: u32, flow_control: bool) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(nix::libc::O_NOCTTY)
+        .open(device)?;
+
+    let fd = file.as_raw_fd();
+    let mut attributes = termios::tcgetattr(fd)?;
+    termios::cfmakeraw(&mut attributes);
+    // Checked when parsing the transport
+    termios::cfsetspeed(&mut attributes, baud_rate(baud).unwrap())?;
+    attributes
+        .control_flags
+        .set(ControlFlags::CRTSCTS, flow_control);
+    attributes.control_flags |= ControlFlags::CLOCAL | ControlFlags::CREAD;
+    termios::tcsetattr(fd, SetArg::TCSANOW, &attributes)?;
+    Ok(file)
+}
+
+impl Transport {
+    /// Transport of the parallel job `index`. TCP ports are offset
+    /// by `stride` for each job, the other transports are shared.
+    pub fn for_job(&self, index: usize, stride: u16) -> Result<Self> {
+        Ok(match self {
+            Transport::Tcp { host, port } => Transport::Tcp {
+                host: host.clone(),
+                port: u16::try_from(index)
+                    .ok()
+                    .and_then(|index| index.checked_mul(stride))
+                    .and_then(|offset| port.checked_add(offset))
+                    .with_context(|| {
+                        format!(
+                            "TCP port {} of job {} with a stride of {} is out of range",
+                            port, index, stride
+                        )
+                    })?,
+            },
+            transport => transport.clone(),
+        })
+    }
+
+    /// Unix sockets and serial devices are a single controller,
+    /// they cannot be used by parallel jobs.
+    pub fn supports_jobs(&self) -> bool {
+        matches!(
+            self,
+            Transport::Tcp { .. } | Transport::Command(_) | Transport::Fake(_)
+        )
+    }
+
+    /// Connect to the controller. The command of a `cmd:` transport
+    /// gets the index of the job in `PTS_BOT_JOB`.
+    pub async fn connect(&self, job: usize) -> io::Result<(Reader, Writer)> {
+        match self {
+            Transport::Tcp { host, port } => {
+                let host = host.clone();
+                let port = *port;
+                let addresses: Vec<_> = blocking::unblock(move || {
+                    std::net::ToSocketAddrs::to_socket_addrs(&(host, port))
+                })
+                .await?
+                .collect();
+                let mut error = io::Error::new(io::ErrorKind::NotFound, "No address");
+                for address in addresses {
+                    match Async::<TcpStream>::connect(address).await {
+                        Ok(stream) => {
+                            let (reader, writer) = io::split(stream);
+                            return Ok((Box::new(reader), Box::new(writer)));
+                        }
+                        Err(e) => error = e,
+                    }
+                }
+                Err(error)
+            }
+            Transport::Unix(path) => {
+                let (reader, writer) = io::split(Async::<UnixStream>::connect(path).await?);
+                Ok((Box::new(reader), Box::new(writer)))
+            }
+            Transport::Serial {
+                device,
+                baud,
+                flow_control,
+            } => {
+                let file = open_serial(device, *baud, *flow_control)?;
+                let (reader, writer) = io::split(Async::new(file)?);
+                Ok((Box::new(reader), Box::new(writer)))
+            }
+            Transport::Command(command) => {
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("PTS_BOT_JOB", job.to_string())
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .process_group(0)
+                    .spawn()?;
+                let stdin = child.stdin.take().unwrap();
+                let stdout = Unblock::new(child.stdout.take().unwrap());
+                Ok((
+                    Box::new(CommandOutput { stdout, child }),
+                    Box::new(Unblock::new(stdin)),
+                ))
+            }
+            Transport::Fake(address) => {
+                let (reader, writer) = io::split(Controller::new(*address));
+                Ok((Box::new(reader), Box::new(writer)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Transport;
+    use async_io::block_on;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+    use libpts::BdAddr;
+    use nix::sys::signal;
+    use nix::unistd::Pid;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse() {
+        let parse = |hci: &str| hci.parse::<Transport>().unwrap();
+        assert_eq!(
+            parse("6402"),
+            Transport::Tcp {
+                host: String::from("localhost"),
+                port: 6402
+            }
+        );
+        assert_eq!(
+            parse("tcp://[::1]:7300"),
+            Transport::Tcp {
+                host: String::from("::1"),
+                port: 7300
+            }
+        );
+        assert_eq!(
+            parse("unix:///tmp/rootcanal.sock"),
+            Transport::Unix(PathBuf::from("/tmp/rootcanal.sock"))
+        );
+        assert_eq!(
+            parse("serial:///dev/ttyUSB0?baud=1000000&flow=rtscts"),
+            Transport::Serial {
+                device: PathBuf::from("/dev/ttyUSB0"),
+                baud: 1000000,
+                flow_control: true
+            }
+        );
+        assert_eq!(
+            parse("serial:///dev/ttyUSB0").to_string(),
+            "serial:///dev/ttyUSB0?baud=115200&flow=none"
+        );
+        assert_eq!(
+            parse("cmd:rootcanal-h4 --port 0"),
+            Transport::Command(String::from("rootcanal-h4 --port 0"))
+        );
+
+        assert_eq!(
+            parse("fake:11:22:33:44:55:66"),
+            Transport::Fake(BdAddr::new([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]))
+        );
+        assert_eq!(parse("fake").to_string(), "fake:00:1b:dc:00:00:01");
+
+        assert!("tcp://localhost".parse::<Transport>().is_err());
+        assert!("fake:1122".parse::<Transport>().is_err());
+        assert!("serial:///dev/ttyUSB0?baud=1234"
+            .parse::<Transport>()
+            .is_err());
+        assert!("serial:///dev/ttyUSB0?parity=odd"
+            .parse::<Transport>()
+            .is_err());
+        assert!("usb:0".parse::<Transport>().is_err());
+    }
+
+    #[test]
+    fn test_for_job() {
+        let tcp = "tcp://rootcanal:6402".parse::<Transport>().unwrap();
+        assert_eq!(
+            tcp.for_job(2, 10).unwrap().to_string(),
+            "tcp://rootcanal:6422"
+        );
+        assert!(tcp.for_job(2, 40000).is_err());
+        assert!(tcp.for_job(70000, 1).is_err());
+        assert!(tcp.supports_jobs());
+
+        let unix = "unix:///tmp/hci".parse::<Transport>().unwrap();
+        assert_eq!(unix.for_job(2, 10).unwrap(), unix);
+        assert!(!unix.supports_jobs());
+    }
+
+    #[test]
+    fn test_command() {
+        let transport = "cmd:echo $PTS_BOT_JOB; cat".parse::<Transport>().unwrap();
+        block_on(async {
+            let (mut reader, mut writer) = transport.connect(3).await.unwrap();
+            writer.write_all(&[0x01, 0x03, 0x0c, 0x00]).await.unwrap();
+            writer.flush().await.unwrap();
+            drop(writer);
+
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output).await.unwrap();
+            assert_eq!(output, [b'3', b'\n', 0x01, 0x03, 0x0c, 0x00]);
+        });
+    }
+
+    #[test]
+    fn test_command_killed() {
+        // The command ignores its input, and would run for a minute
+        let transport = "cmd:echo $$; exec sleep 60".parse::<Transport>().unwrap();
+        let pid = block_on(async {
+            let (mut reader, writer) = transport.connect(0).await.unwrap();
+            let mut output = Vec::new();
+            let mut byte = [0u8];
+            while output.last() != Some(&b'\n') {
+                reader.read_exact(&mut byte).await.unwrap();
+                output.push(byte[0]);
+            }
+            drop(writer);
+            drop(reader);
+            String::from_utf8(output).unwrap().trim().parse().unwrap()
+        });
+
+        // Killed and reaped, the process does not exist anymore
+        assert_eq!(
+            signal::kill(Pid::from_raw(pid), None),
+            Err(nix::Error::ESRCH)
+        );
+    }
+
+    #[test]
+    fn test_fake() {
+        let transport = "fake:11:22:33:44:55:66".parse::<Transport>().unwrap();
+        block_on(async {
+            let (mut reader, mut writer) = transport.connect(0).await.unwrap();
+            writer.write_all(&[0x01, 0x09, 0x10, 0x00]).await.unwrap();
+
+            let mut output = [0u8; 13];
+            reader.read_exact(&mut output).await.unwrap();
+            assert_eq!(
+                output,
+                [0x04, 0x0e, 0x0a, 0x01, 0x09, 0x10, 0x00, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]
+            );
+        });
+    }
+
+    #[test]
+    fn test_fake_initialization() {
+        let transport = "fake:11:22:33:44:55:66".parse::<Transport>().unwrap();
+        block_on(async {
+            let (mut reader, mut writer) = transport.connect(0).await.unwrap();
+
+            // Commands sent by the PTS when it initializes its stack,
+            // with their parameters
+            let commands: [(u16, &[u8]); 14] = [
+                (0x0c03, &[]),        // Reset
+                (0x1003, &[]),        // Read Local Supported Features
+                (0x1001, &[]),        // Read Local Version Information
+                (0x1009, &[]),        // Read BD_ADDR
+                (0x1005, &[]),        // Read Buffer Size
+                (0x0c23, &[]),        // Read Class of Device
+                (0x0c14, &[]),        // Read Local Name
+                (0x0c25, &[]),        // Read Voice Setting
+                (0x0c01, &[0xff; 8]), // Set Event Mask
+                (0x1002, &[]),        // Read Local Supported Commands
+                (0x1004, &[0x01]),    // Read Local Extended Features
+                (0x2002, &[]),        // LE Read Buffer Size
+                (0x2003, &[]),        // LE Read Local Supported Features
+                (0x201c, &[]),        // LE Read Supported States
+            ];
+
+            for (opcode, parameters) in commands {
+                let mut command = vec![0x01];
+                command.extend(opcode.to_le_bytes());
+                command.push(parameters.len() as u8);
+                command.extend(parameters);
+                writer.write_all(&command).await.unwrap();
+
+                // Command Complete of the command, with a success status
+                let mut header = [0u8; 3];
+                reader.read_exact(&mut header).await.unwrap();
+                assert_eq!(header[..2], [0x04, 0x0e]);
+                let mut event = vec![0u8; header[2] as usize];
+                reader.read_exact(&mut event).await.unwrap();
+                assert_eq!(
+                    event[1..4],
+                    [opcode.to_le_bytes()[0], opcode.to_le_bytes()[1], 0x00]
+                );
+
+                if opcode == 0x1009 {
+                    let address =
+                        BdAddr::new([event[9], event[8], event[7], event[6], event[5], event[4]]);
+                    assert_eq!(address.to_string(), "11:22:33:44:55:66");
+                }
+            }
+        });
+    }
+}
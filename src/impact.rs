@@ -0,0 +1,141 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+
+use libpts::{Expression, Profile};
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// ICS item of the mapping of a test whose value differs
+/// between the two configs, `None` when it is not defined.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Cause {
+    pub name: String,
+    pub old: Option<bool>,
+    pub new: Option<bool>,
+}
+
+/// Test enabled or disabled by the new config.
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub test: String,
+    pub enabled: bool,
+    pub causes: Vec<Cause>,
+}
+
+/// ICS values of a config for a profile, looked up as given and
+/// uppercased like when they are applied to PTS.
+pub fn ics(config: &Config, profile: &str) -> HashMap<String, bool> {
+    let mut ics = HashMap::new();
+    let profile_ics = config.profile_ics.get(profile).into_iter().flatten();
+    for (name, &value) in config.ics.iter().chain(profile_ics) {
+        ics.insert(name.clone(), value);
+        ics.insert(name.to_uppercase(), value);
+    }
+    ics
+}
+
+/// Items of the mapping whose value changes.
+pub fn causes(
+    mapping: &str,
+    old: impl Fn(&str) -> Option<bool>,
+    new: impl Fn(&str) -> Option<bool>,
+) -> Vec<Cause> {
+    let expression = match Expression::parse(mapping) {
+        Ok(expression) => expression,
+        Err(_) => return Vec::new(),
+    };
+    expression
+        .items()
+        .into_iter()
+        .filter_map(|name| {
+            let (old, new) = (old(name), new(name));
+            (old != new).then(|| Cause {
+                name: name.to_owned(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// Tests of the profile enabled or disabled when going from
+/// the `old` ICS values to the `new` ones, in ETS order.
+pub fn changes(
+    profile: &Profile,
+    old: &HashMap<String, bool>,
+    new: &HashMap<String, bool>,
+) -> Vec<Change> {
+    let enabled_old: HashSet<String> = profile.tests_with_ics(old).collect();
+    let enabled_new: HashSet<String> = profile.tests_with_ics(new).collect();
+    let value = |ics: &HashMap<String, bool>, name: &str| {
+        ics.get(name).copied().or_else(|| profile.ics_default(name))
+    };
+
+    profile
+        .test_cases()
+        .into_iter()
+        .filter_map(|testcase| {
+            let enabled = enabled_new.contains(testcase.name);
+            (enabled != enabled_old.contains(testcase.name)).then(|| Change {
+                test: testcase.name.to_owned(),
+                enabled,
+                causes: causes(
+                    testcase.mapping,
+                    |name| value(old, name),
+                    |name| value(new, name),
+                ),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{causes, Cause};
+
+    #[test]
+    fn test_causes() {
+        let old = |name: &str| match name {
+            "TSPC_HFP_1_1" => Some(true),
+            "TSPC_HFP_2_1" => Some(false),
+            _ => None,
+        };
+        let new = |name: &str| match name {
+            "TSPC_HFP_1_1" => Some(true),
+            "TSPC_HFP_2_1" => Some(true),
+            "TSPC_HFP_2_2" => Some(false),
+            _ => None,
+        };
+
+        assert_eq!(
+            causes("TSPC_HFP_1_1 AND (TSPC_HFP_2_1 OR TSPC_HFP_2_2)", old, new),
+            [
+                Cause {
+                    name: String::from("TSPC_HFP_2_1"),
+                    old: Some(false),
+                    new: Some(true),
+                },
+                Cause {
+                    name: String::from("TSPC_HFP_2_2"),
+                    old: None,
+                    new: Some(false),
+                },
+            ]
+        );
+        assert!(causes("TSPC_HFP_1_1", old, new).is_empty());
+    }
+}
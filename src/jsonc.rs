@@ -1,136 +1,220 @@
-use std::io::{Read, Result};
-
-enum State {
-    Plain,
-    Quote,
-    Slash,
-    Comment,
+use std::fmt;
+use std::io::{self, Read};
+
+use serde::de::DeserializeOwned;
+
+/// Error of a JSONC document, with the position of syntax errors.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
 }
 
-// Reader to filter out single line comments from the input stream
-// to make the configuration compatible with standard JSON.
-pub struct Reader<R: Read> {
-    reader: R,
-    state: State,
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "{} at line {} column {}", message, line, column),
+        }
+    }
 }
 
-impl<R: Read> Reader<R> {
-    pub fn new(reader: R) -> Self {
-        Reader {
-            reader,
-            state: State::Plain,
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Syntax { .. } => None,
         }
     }
+}
 
-    // Filter comments in input buffer. Returns the number of bytes
-    // left in the buffer after filtering.
-    fn filter(&mut self, buf: &mut [u8]) -> usize {
-        let mut head = 0;
-
-        fn write(buf: &mut [u8], head: &mut usize, c: u8) {
-            buf[*head] = c;
-            *head += 1;
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            return Error::Io(e.into());
+        }
+        let message = e.to_string();
+        let suffix = format!(" at line {} column {}", e.line(), e.column());
+        Error::Syntax {
+            line: e.line(),
+            column: e.column(),
+            message: message.strip_suffix(&suffix).unwrap_or(&message).to_owned(),
         }
+    }
+}
 
-        use State::*;
-        for cursor in 0..buf.len() {
-            let c = buf[cursor];
+// Position of a byte offset, both starting at 1 like serde_json.
+fn syntax_error(input: &[u8], offset: usize, message: &str) -> Error {
+    let before = &input[..offset];
+    let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+    let line_start = before
+        .iter()
+        .rposition(|&c| c == b'\n')
+        .map_or(0, |index| index + 1);
+    Error::Syntax {
+        line,
+        column: offset - line_start + 1,
+        message: message.to_owned(),
+    }
+}
 
-            self.state = match self.state {
-                Plain => match c as char {
-                    '/' => Slash,
-                    '"' => {
-                        write(buf, &mut head, c);
-                        Quote
-                    }
-                    _ => {
-                        write(buf, &mut head, c);
-                        Plain
-                    }
-                },
-                Quote => {
-                    write(buf, &mut head, c);
-                    match c as char {
-                        '"' => Plain,
-                        _ => Quote,
+/// Replace the comments by spaces, keeping their line breaks,
+/// so that the positions of what is left do not change.
+fn strip_comments(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = input.to_vec();
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'"' => index = skip_string(input, index),
+            b'/' => {
+                let start = index;
+                let end = match input.get(index + 1) {
+                    Some(b'/') => input[index..]
+                        .iter()
+                        .position(|&c| c == b'\n')
+                        .map_or(input.len(), |end| index + end),
+                    Some(b'*') => input[index + 2..]
+                        .windows(2)
+                        .position(|window| window == b"*/")
+                        .map(|end| index + 2 + end + 2)
+                        .ok_or_else(|| syntax_error(input, start, "unterminated block comment"))?,
+                    _ => return Err(syntax_error(input, start, "unexpected '/'")),
+                };
+                for c in output[start..end].iter_mut() {
+                    if *c != b'\n' {
+                        *c = b' ';
                     }
                 }
-                Slash => match c as char {
-                    '/' => Comment,
-                    _ => {
-                        // Normally would write back the '/' but this can cause
-                        // an OOB if the reader is reading to [u8; 1]. Luckily
-                        // standalone '/' characters are not expected in JSON
-                        // so we can raise an early error here.
-                        //
-                        // write(buf, &mut head, '/' as u8);
-                        // write(buf, &mut head, c);
-                        // Plain
-                        unreachable!("unexpected '/' in plain json text")
-                    }
-                },
-                Comment => match c as char {
-                    '\n' => {
-                        write(buf, &mut head, c);
-                        Plain
-                    }
-                    _ => Comment,
-                },
+                index = end;
             }
+            _ => index += 1,
         }
+    }
+    Ok(output)
+}
 
-        head
+// Index after the end of the string starting at `start`,
+// or the end of the input when it is not terminated.
+fn skip_string(input: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    while index < input.len() {
+        match input[index] {
+            b'\\' => index += 2,
+            b'"' => return index + 1,
+            _ => index += 1,
+        }
     }
+    input.len()
 }
 
-impl<R: Read> Read for Reader<R> {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let mut head = 0;
-        loop {
-            let len = self.reader.read(&mut buf[head..])?;
-            if len == 0 {
-                break;
+/// Replace the commas before a closing bracket by spaces.
+fn strip_trailing_commas(input: &mut [u8]) {
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'"' => index = skip_string(input, index),
+            b',' => {
+                let next = input[index + 1..].iter().find(|c| !c.is_ascii_whitespace());
+                if matches!(next, Some(b'}') | Some(b']')) {
+                    input[index] = b' ';
+                }
+                index += 1;
             }
-
-            head += self.filter(&mut buf[head..head + len]);
+            _ => index += 1,
         }
-        Ok(head)
     }
 }
 
+/// Parse a JSON document which can contain `//` and `/* */` comments,
+/// and trailing commas in objects and arrays.
+pub fn from_slice<T: DeserializeOwned>(input: &[u8]) -> Result<T, Error> {
+    let mut json = strip_comments(input)?;
+    strip_trailing_commas(&mut json);
+    Ok(serde_json::from_slice(&json)?)
+}
+
+pub fn from_reader<T: DeserializeOwned>(mut reader: impl Read) -> Result<T, Error> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input).map_err(Error::Io)?;
+    from_slice(&input)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::jsonc::Reader;
-    use std::io::Read;
-
-    fn test(input: &str, expected_output: &str) {
-        let mut reader = Reader::new(input.as_bytes());
-        let mut output = [0; 256];
-        let result = reader.read(&mut output);
+    use super::{from_slice, Error};
+    use serde_json::{json, Value};
 
-        assert!(result.is_ok(), "jsonc read returned an error");
-        let output_len = result.unwrap();
+    fn parse(input: &str) -> Value {
+        from_slice(input.as_bytes()).unwrap()
+    }
 
-        assert_eq!(expected_output.len(), output_len);
-        assert_eq!(expected_output.as_bytes(), &output[0..output_len]);
+    fn error(input: &str) -> (usize, usize, String) {
+        match from_slice::<Value>(input.as_bytes()) {
+            Err(Error::Syntax {
+                line,
+                column,
+                message,
+            }) => (line, column, message),
+            result => panic!("Expected a syntax error, got {:?}", result),
+        }
     }
 
     #[test]
     fn plain() {
-        test("abcd\nefgh", "abcd\nefgh");
+        assert_eq!(parse(r#"{"a": [1, 2]}"#), json!({"a": [1, 2]}));
     }
 
     #[test]
     fn quote() {
-        test("\"abcd\"\nefgh", "\"abcd\"\nefgh");
-        test("\"ab/cd\"\nefgh", "\"ab/cd\"\nefgh");
-        test("\"ab//cd\"\nefgh", "\"ab//cd\"\nefgh");
+        assert_eq!(
+            parse(r#"["ab/cd", "ab//cd", "/*"]"#),
+            json!(["ab/cd", "ab//cd", "/*"])
+        );
+        assert_eq!(
+            parse(r#"["ab\"// cd", "ef\\", "g,]"] // h"#),
+            json!(["ab\"// cd", "ef\\", "g,]"])
+        );
     }
 
     #[test]
     fn comment() {
-        test("// abcd\nefgh", "\nefgh");
-        test("abcd // efgh\nijkl", "abcd \nijkl");
-        test("\"abcd\" // efgh\nijkl", "\"abcd\" \nijkl");
+        assert_eq!(parse("// abcd\n[1]"), json!([1]));
+        assert_eq!(parse("[1, // efgh\n2]"), json!([1, 2]));
+        assert_eq!(parse("[1, /* a\n * b */ 2] /**/"), json!([1, 2]));
+        assert_eq!(parse("[1] // no line break"), json!([1]));
+    }
+
+    #[test]
+    fn trailing_comma() {
+        assert_eq!(
+            parse("{\"a\": [1, 2,], \"b\": {\"c\": 3, /* d */ },\n}"),
+            json!({"a": [1, 2], "b": {"c": 3}})
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error("{\n  \"a\": 1 / 2\n}"),
+            (2, 10, String::from("unexpected '/'"))
+        );
+        assert_eq!(
+            error("[1,\n /* 2 ]"),
+            (2, 2, String::from("unterminated block comment"))
+        );
+        // Comments do not shift the positions of serde_json
+        assert_eq!(
+            error("/* a\n b */ {\"a\": tru}"),
+            (2, 16, String::from("expected ident"))
+        );
+        assert_eq!(error("[1,,]").2, "trailing comma");
     }
 }
@@ -0,0 +1,377 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::time::Duration;
+
+use crate::test::{Attempt, TestExecution, TestResult};
+
+// Escape text for use in both XML attributes and character data.
+// Characters which are not allowed in XML 1.0 documents, like the
+// ANSI escape sequences of the colored event log, are removed.
+fn escape(text: &str) -> Cow<'_, str> {
+    if !text
+        .chars()
+        .any(|c| matches!(c, '<' | '>' | '&' | '"' | '\'') || is_invalid(c))
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if is_invalid(c) => {}
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn is_invalid(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
+
+// Remove the terminal styling sequences (ESC [ ... final byte)
+// written by the libpts logger.
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+struct Suite<'a> {
+    name: String,
+    executions: Vec<&'a TestExecution>,
+}
+
+impl Suite<'_> {
+    fn count(&self, predicate: impl Fn(&TestResult) -> bool) -> usize {
+        self.executions
+            .iter()
+            .filter(|execution| predicate(&execution.result))
+            .count()
+    }
+
+    fn duration(&self) -> Duration {
+        self.executions
+            .iter()
+            .map(|execution| execution.total_duration())
+            .sum()
+    }
+}
+
+// Group the executions into one suite per profile and ETS group,
+// keeping the suites and the test cases in their run order.
+fn suites(results: &[TestExecution]) -> Vec<Suite<'_>> {
+    let mut suites: Vec<Suite> = Vec::new();
+    for execution in results {
+        let name = std::iter::once(&execution.profile)
+            .chain(execution.groups.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(".");
+
+        match suites.iter_mut().find(|suite| suite.name == name) {
+            Some(suite) => suite.executions.push(execution),
+            None => suites.push(Suite {
+                name,
+                executions: vec![execution],
+            }),
+        }
+    }
+    suites
+}
+
+fn is_failure(result: &TestResult) -> bool {
+    matches!(result, TestResult::Fail)
+}
+
+fn is_error(result: &TestResult) -> bool {
+    matches!(result, TestResult::Error(_))
+}
+
+fn is_skipped(result: &TestResult) -> bool {
+    matches!(
+        result,
+        TestResult::Inconclusive | TestResult::None | TestResult::Aborted
+    )
+}
+
+fn write_testcase(to: &mut impl Write, classname: &str, execution: &TestExecution) -> Result<()> {
+    write!(
+        to,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+        escape(&execution.name),
+        escape(classname),
+        seconds(execution.total_duration())
+    )?;
+
+    if matches!(execution.result, TestResult::Pass)
+        && execution.log.is_empty()
+        && execution.retries.is_empty()
+    {
+        return writeln!(to, "/>");
+    }
+    writeln!(to, ">")?;
+
+    match execution.result {
+        TestResult::Pass | TestResult::Flaky => {}
+        TestResult::Fail => writeln!(to, "      <failure message=\"FAIL\" type=\"FAIL\"/>")?,
+        TestResult::Inconclusive => writeln!(to, "      <skipped message=\"INCONC\"/>")?,
+        TestResult::None => writeln!(to, "      <skipped message=\"NONE\"/>")?,
+        TestResult::Aborted => writeln!(to, "      <skipped message=\"ABORTED\"/>")?,
+        TestResult::Error(ref e) => writeln!(
+            to,
+            "      <error message=\"{}\" type=\"Error\">{}</error>",
+            escape(&e.to_string()),
+            escape(&format!("{:?}", e))
+        )?,
+    }
+
+    // Retries are reported with the Maven Surefire rerun elements.
+    let prefix = if execution.result.is_success() {
+        "flaky"
+    } else {
+        "rerun"
+    };
+    for attempt in execution.retries.iter() {
+        write_attempt(to, prefix, attempt)?;
+    }
+
+    if !execution.log.is_empty() {
+        writeln!(
+            to,
+            "      <system-out>{}</system-out>",
+            escape(&strip_ansi(&execution.log))
+        )?;
+    }
+
+    writeln!(to, "    </testcase>")
+}
+
+fn write_attempt(to: &mut impl Write, prefix: &str, attempt: &Attempt) -> Result<()> {
+    let (element, message, stack_trace) = match attempt.result {
+        TestResult::Error(ref e) => ("Error", e.to_string(), Some(format!("{:?}", e))),
+        TestResult::Fail => ("Failure", String::from("FAIL"), None),
+        TestResult::Inconclusive => ("Failure", String::from("INCONC"), None),
+        TestResult::None => ("Failure", String::from("NONE"), None),
+        TestResult::Pass | TestResult::Flaky => unreachable!("successful attempt retried"),
+        TestResult::Aborted => unreachable!("aborted attempt retried"),
+    };
+
+    writeln!(
+        to,
+        "      <{}{} message=\"{}\" type=\"{}\">",
+        prefix,
+        element,
+        escape(&message),
+        element
+    )?;
+    if let Some(stack_trace) = stack_trace {
+        writeln!(
+            to,
+            "        <stackTrace>{}</stackTrace>",
+            escape(&stack_trace)
+        )?;
+    }
+    if !attempt.log.is_empty() {
+        writeln!(
+            to,
+            "        <system-out>{}</system-out>",
+            escape(&strip_ansi(&attempt.log))
+        )?;
+    }
+    writeln!(to, "      </{}{}>", prefix, element)
+}
+
+/// Write the executions as a JUnit XML report.
+pub fn write(to: &mut impl Write, results: &[TestExecution]) -> Result<()> {
+    let suites = suites(results);
+    let count = |predicate: fn(&TestResult) -> bool| {
+        results
+            .iter()
+            .filter(|execution| predicate(&execution.result))
+            .count()
+    };
+
+    writeln!(to, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        to,
+        "<testsuites name=\"pts-bot\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+        results.len(),
+        count(is_failure),
+        count(is_error),
+        count(is_skipped),
+        seconds(results.iter().map(|execution| execution.total_duration()).sum())
+    )?;
+
+    for suite in suites {
+        writeln!(
+            to,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+            escape(&suite.name),
+            suite.executions.len(),
+            suite.count(is_failure),
+            suite.count(is_error),
+            suite.count(is_skipped),
+            seconds(suite.duration())
+        )?;
+        for execution in suite.executions.iter() {
+            write_testcase(to, &suite.name, execution)?;
+        }
+        writeln!(to, "  </testsuite>")?;
+    }
+
+    writeln!(to, "</testsuites>")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape, strip_ansi, write};
+    use crate::test::{Attempt, TestExecution, TestResult};
+    use std::time::Duration;
+
+    fn execution(groups: &[&str], name: &str, result: TestResult) -> TestExecution {
+        TestExecution {
+            profile: String::from("A2DP"),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            name: name.to_owned(),
+            result,
+            duration: Duration::from_millis(1500),
+            log: String::new(),
+            retries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a < b && \"c\""),
+            "a &lt; b &amp;&amp; &quot;c&quot;"
+        );
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("bell\x07"), "bell");
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[1m\x1b[38;5;42mStep\x1b[0m done"),
+            "Step done"
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let mut error = execution(
+            &["SRC"],
+            "A2DP/SRC/AS/BV-03-I",
+            TestResult::Error(anyhow::anyhow!("Timeout").context("Runtime Error")),
+        );
+        error.log = String::from("\x1b[1mLog\x1b[0m <PDU>");
+
+        let results = vec![
+            execution(&["SRC", "AS"], "A2DP/SRC/AS/BV-01-I", TestResult::Pass),
+            execution(&["SRC"], "A2DP/SRC/CC/BV-09-I", TestResult::Fail),
+            execution(
+                &["SRC", "AS"],
+                "A2DP/SRC/AS/BV-02-I",
+                TestResult::Inconclusive,
+            ),
+            error,
+        ];
+
+        let mut output = Vec::new();
+        write(&mut output, &results).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            output,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pts-bot" tests="4" failures="1" errors="1" skipped="1" time="6.000">
+  <testsuite name="A2DP.SRC.AS" tests="2" failures="0" errors="0" skipped="1" time="3.000">
+    <testcase name="A2DP/SRC/AS/BV-01-I" classname="A2DP.SRC.AS" time="1.500"/>
+    <testcase name="A2DP/SRC/AS/BV-02-I" classname="A2DP.SRC.AS" time="1.500">
+      <skipped message="INCONC"/>
+    </testcase>
+  </testsuite>
+  <testsuite name="A2DP.SRC" tests="2" failures="1" errors="1" skipped="0" time="3.000">
+    <testcase name="A2DP/SRC/CC/BV-09-I" classname="A2DP.SRC" time="1.500">
+      <failure message="FAIL" type="FAIL"/>
+    </testcase>
+    <testcase name="A2DP/SRC/AS/BV-03-I" classname="A2DP.SRC" time="1.500">
+      <error message="Runtime Error" type="Error">Runtime Error
+
+Caused by:
+    Timeout</error>
+      <system-out>Log &lt;PDU&gt;</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn test_write_flaky() {
+        let mut flaky = execution(&["SRC"], "A2DP/SRC/REL/BV-01-I", TestResult::Flaky);
+        flaky.retries = vec![Attempt {
+            result: TestResult::Inconclusive,
+            duration: Duration::from_secs(1),
+            log: String::from("INCONC"),
+        }];
+
+        let mut output = Vec::new();
+        write(&mut output, &[flaky]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            output,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pts-bot" tests="1" failures="0" errors="0" skipped="0" time="2.500">
+  <testsuite name="A2DP.SRC" tests="1" failures="0" errors="0" skipped="0" time="2.500">
+    <testcase name="A2DP/SRC/REL/BV-01-I" classname="A2DP.SRC" time="2.500">
+      <flakyFailure message="INCONC" type="Failure">
+        <system-out>INCONC</system-out>
+      </flakyFailure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reader of the `.pts` ICS files exported by Launch Studio:
+//!
+//! ```xml
+//! <profile>
+//!   <name>A2DP</name>
+//!   <item><table>2</table><row>1</row></item>
+//! </profile>
+//! ```
+//!
+//! Each item is a supported feature, `TSPC_A2DP_2_1` is TRUE.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Ics {
+    /// Names of the profiles, in file order.
+    pub profiles: Vec<String>,
+    pub ics: HashMap<String, bool>,
+}
+
+#[derive(Default)]
+struct Item {
+    table: Option<String>,
+    row: Option<String>,
+}
+
+pub fn parse(reader: impl Read) -> Result<Ics> {
+    let mut result = Ics::default();
+    // Elements from the root to the current one.
+    let mut path: Vec<String> = Vec::new();
+    let mut profile: Option<String> = None;
+    let mut items: Vec<Item> = Vec::new();
+    let mut item: Option<Item> = None;
+
+    for event in EventReader::new(reader) {
+        match event.context("Invalid Launch Studio file")? {
+            XmlEvent::StartElement { name, .. } => {
+                match name.local_name.as_str() {
+                    "profile" => {
+                        profile = None;
+                        items.clear();
+                    }
+                    "item" => item = Some(Item::default()),
+                    _ => {}
+                }
+                path.push(name.local_name);
+            }
+            XmlEvent::Characters(text) => {
+                let text = text.trim().to_owned();
+                let parent = path.iter().rev().nth(1).map(String::as_str);
+                match (path.last().map(String::as_str), parent, item.as_mut()) {
+                    (Some("table"), Some("item"), Some(item)) => item.table = Some(text),
+                    (Some("row"), Some("item"), Some(item)) => item.row = Some(text),
+                    (Some("name"), Some("profile"), _) if profile.is_none() => profile = Some(text),
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                path.pop();
+                match name.local_name.as_str() {
+                    "item" => items.extend(item.take()),
+                    "profile" => {
+                        let name = profile.take().context("Profile without name")?;
+                        for item in items.drain(..) {
+                            match item {
+                                Item {
+                                    table: Some(table),
+                                    row: Some(row),
+                                } => {
+                                    result
+                                        .ics
+                                        .insert(format!("TSPC_{}_{}_{}", name, table, row), true);
+                                }
+                                _ => bail!("Item without table or row in profile {}", name),
+                            }
+                        }
+                        if !result.profiles.contains(&name) {
+                            result.profiles.push(name);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let pts = r#"<?xml version="1.0" encoding="utf-8"?>
+            <pts>
+              <profiles>
+                <profile>
+                  <name>A2DP</name>
+                  <version>1.4</version>
+                  <items>
+                    <item><table>1</table><row>1</row></item>
+                    <item><table>2</table><row>1a</row></item>
+                  </items>
+                </profile>
+                <profile>
+                  <name>AVRCP</name>
+                  <items>
+                    <item><table>4</table><row>5</row></item>
+                  </items>
+                </profile>
+              </profiles>
+            </pts>"#;
+
+        let ics = parse(pts.as_bytes()).unwrap();
+        assert_eq!(ics.profiles, ["A2DP", "AVRCP"]);
+        assert_eq!(ics.ics.len(), 3);
+        assert!(ics.ics["TSPC_A2DP_1_1"]);
+        assert!(ics.ics["TSPC_A2DP_2_1a"]);
+        assert!(ics.ics["TSPC_AVRCP_4_5"]);
+
+        assert!(
+            parse("<profile><name>A2DP</name><item><row>1</row></item></profile>".as_bytes())
+                .is_err()
+        );
+        assert!(parse("<profile><name>A2DP</name>".as_bytes()).is_err());
+    }
+}
@@ -13,101 +13,85 @@
 // limitations under the License.
 
 use std::collections::{HashMap, HashSet};
-use std::convert::TryInto;
 use std::fs::File;
-use std::future::Future;
-use std::io::{stdout, BufReader};
-use std::net::{Ipv4Addr, TcpStream};
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::task::Poll;
-use std::time::Duration;
 
-use anyhow::{Context, Error, Result};
-use libpts::{final_verdict, logger, map_with_stack, BdAddr, Interaction, HCI, PTS};
-use serde::Deserialize;
-use structopt::StructOpt;
-
-use futures_lite::{future, io, pin, ready, stream, FutureExt, Stream, StreamExt};
-
-use async_io::{block_on, Async};
+use anyhow::{bail, Context, Result};
+use libpts::PTS;
+use structopt::{clap::AppSettings, StructOpt};
 
 use async_ctrlc::CtrlC;
 
-use blocking::unblock;
-
 mod python;
-use python::PythonIUT;
 
+mod artifacts;
+mod baseline;
+mod btsnoop;
+mod commands;
+mod config;
+mod explain;
+mod fault;
+mod hci;
+mod impact;
 mod jsonc;
+mod junit;
+mod launch_studio;
+mod runner;
+mod selector;
+mod solver;
+mod state;
+mod template;
 mod test;
-
-async fn connect_to_hci(port: HCI) -> std::io::Result<()> {
-    let opts = Opts::from_args();
-    let hci_port = opts.hci;
-    let tcp = Async::<TcpStream>::connect((Ipv4Addr::LOCALHOST, hci_port)).await?;
-
-    let (hcirx, hcitx) = io::split(port);
-    let (tcprx, tcptx) = io::split(tcp);
-
-    future::or(io::copy(hcirx, tcptx), io::copy(tcprx, hcitx)).await?;
-
-    println!("HCI ended");
-
-    Ok(())
-}
-
-fn abortable<T>(
-    mut stream: impl Stream<Item = T> + Unpin,
-    mut signal: impl Future<Output = ()> + Unpin,
-) -> impl Stream<Item = T> + Unpin {
-    stream::poll_fn(move |cx| match stream.poll_next(cx) {
-        Poll::Pending => signal.poll(cx).map(|_| None),
-        val => val,
-    })
-}
-
-fn take_until<T>(
-    mut stream: impl Stream<Item = T> + Unpin,
-    mut predicate: impl FnMut(&T) -> bool,
-) -> impl Stream<Item = T> + Unpin {
-    let mut flag = false;
-    stream::poll_fn(move |cx| {
-        if flag {
-            Poll::Ready(None)
-        } else {
-            Poll::Ready(if let Some(x) = ready!(stream.poll_next(cx)) {
-                if predicate(&x) {
-                    flag = true;
-                }
-                Some(x)
-            } else {
-                None
-            })
-        }
-    })
-}
-
-#[derive(Debug, Deserialize)]
-struct Config {
-    ics: HashMap<String, bool>,
-    ixit: HashMap<String, HashMap<String, String>>,
-    skip: Option<Vec<String>>,
-}
+mod validate;
+
+use baseline::Baseline;
+use baseline::Verdict;
+use commands::Command;
+use config::{Config, IcsOverride, IxitOverride};
+use hci::Transport;
+use selector::Selector;
+use state::State;
+use test::{TestCase, TestExecution};
+use validate::{Definitions, Severity};
 
 #[derive(Debug, StructOpt)]
 #[structopt(
     name = "pts-bot",
-    about = "Automating PTS tests in virtual environments"
+    about = "Automating PTS tests in virtual environments",
+    setting = AppSettings::SubcommandsNegateReqs
 )]
 struct Opts {
-    /// Config file path
-    #[structopt(short, long, parse(from_os_str))]
-    config: Option<PathBuf>,
+    /// Config file path, either JSONC or a Launch Studio .pts ICS file.
+    /// Can be repeated, each config overlays the previous ones
+    #[structopt(short, long, parse(from_os_str), number_of_values = 1)]
+    config: Vec<PathBuf>,
+
+    /// Set an ICS value, over the config files including their
+    /// profile sections and test overrides. Can be repeated
+    #[structopt(long, number_of_values = 1)]
+    ics: Vec<IcsOverride>,
+    /// Set an IXIT value of a profile, or of all the profiles with
+    /// default.NAME=VALUE, over the config files including their
+    /// profile sections and test overrides. Can be repeated
+    #[structopt(long, number_of_values = 1)]
+    ixit: Vec<IxitOverride>,
+    /// Treat config warnings, like unknown ICS or IXIT names, as errors
+    #[structopt(long)]
+    strict_config: bool,
 
-    /// HCI port
+    /// HCI transport to the controller: PORT or tcp://HOST:PORT,
+    /// unix://PATH, serial://DEVICE?baud=RATE&flow=rtscts|none,
+    /// cmd:COMMAND with H4 on its stdin and stdout, or fake[:ADDRESS]
+    /// for an in-process controller only answering the PTS initialization
     #[structopt(short, long, default_value = "6402")]
-    hci: u16,
+    hci: Transport,
+
+    /// Inject a fault on the HCI link: ACTION:MATCH[,MATCH...], with
+    /// ACTION drop, delay=MS, duplicate or corrupt, and MATCH
+    /// type=command|acl|sco|event|iso, opcode=N, event=N or acl=HANDLE.
+    /// Can be repeated, the first rule matching a packet applies
+    #[structopt(long, number_of_values = 1)]
+    hci_fault: Vec<fault::Rule>,
 
     /// Selects the Python module implementing PTS interactions
     #[structopt(short, long, default_value = "mmi2grpc")]
@@ -117,10 +101,25 @@ struct Opts {
     #[structopt(short, long)]
     list: bool,
 
+    /// Number of times a test which did not pass is run again
+    #[structopt(long, default_value = "0")]
+    retries: usize,
+
     /// Stop after first non sucessfull result
     #[structopt(long)]
     fail_fast: bool,
 
+    /// Number of tests run in parallel, each job uses
+    /// its own copy of the PTS installation. The IUT gets
+    /// the index of its job, mmi2grpc connects to the gRPC
+    /// port 8999 plus this index
+    #[structopt(short, long, default_value = "1")]
+    jobs: usize,
+
+    /// Offset between the TCP HCI ports of two consecutive jobs
+    #[structopt(long, default_value = "10")]
+    hci_port_stride: u16,
+
     /// Test inactivity timeout
     #[structopt(short = "t", long, default_value = "60")]
     inactivity_timeout: u64,
@@ -133,12 +132,64 @@ struct Opts {
     #[structopt(long, parse(from_os_str))]
     pts_cache: Option<PathBuf>,
 
-    /// All tests under this prefix will be run.
-    /// The prefix must include the profile.
-    test_prefix: String,
+    /// Write a JUnit XML report of the run to this path
+    #[structopt(long, parse(from_os_str))]
+    junit: Option<PathBuf>,
+
+    /// Write the server output, events, MMI exchanges, HCI capture
+    /// and verdict of each test into a subdirectory of this directory
+    #[structopt(long, parse(from_os_str))]
+    artifacts: Option<PathBuf>,
+
+    /// Compare the results with the expected verdicts of this file,
+    /// and only fail on regressions and unexpected passes
+    #[structopt(long, parse(from_os_str))]
+    baseline: Option<PathBuf>,
+
+    /// Rewrite the baseline file from the results of this run
+    #[structopt(long, requires = "baseline")]
+    update_baseline: bool,
 
-    /// IUT parameters
+    /// Index of the shard to run, from 0 to shard-count - 1
+    #[structopt(long, default_value = "0")]
+    shard_index: usize,
+
+    /// Split the selected tests into this number of disjoint shards
+    #[structopt(long, default_value = "1")]
+    shard_count: usize,
+
+    /// Save the result of each test to this file as soon as it ends
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["resume", "rerun_failed"])]
+    state: Option<PathBuf>,
+
+    /// Skip the tests which already have a verdict in this state file,
+    /// the results of the run are appended to it
+    #[structopt(long, parse(from_os_str), conflicts_with = "rerun_failed")]
+    resume: Option<PathBuf>,
+
+    /// Only run the tests whose last result in this state file is not a pass,
+    /// the results of the run are appended to it
+    #[structopt(long, parse(from_os_str))]
+    rerun_failed: Option<PathBuf>,
+
+    /// Exclude the tests matching this selector, can be repeated
+    #[structopt(short = "x", long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Selects the tests to run, each selector must start with the profile.
+    /// A selector is either a prefix (A2DP/SRC), a glob matching the whole
+    /// test name (AVDTP/SRC/ACP/SIG/SMG/BV-0[5-9]-C) or a regex
+    /// prefixed with re: (re:^HFP/AG/SLC/)
+    #[structopt(required = true)]
+    selectors: Vec<String>,
+
+    /// IUT parameters, they must follow -- to not be taken
+    /// for selectors (pts-bot A2DP -- --flag value)
+    #[structopt(last = true)]
     args: Vec<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
 fn main() -> Result<()> {
@@ -172,61 +223,227 @@ fn main() -> Result<()> {
         })
         .context("Failed to get cache dir")?;
 
-    println!("Installing to {}", std::path::absolute(&cache)?.display());
+    let cache = std::path::absolute(&cache)?;
 
-    let mut pts =
-        PTS::install(std::path::absolute(&cache)?, installer).context("Failed to create PTS")?;
+    // Keep stdout for the output of the commands.
+    if opts.command.is_some() {
+        eprintln!("Installing to {}", cache.display());
+    } else {
+        println!("Installing to {}", cache.display());
+    }
+
+    let mut pts = PTS::install(cache.clone(), installer).context("Failed to create PTS")?;
     let mut skip = HashSet::new();
 
-    let profile_name = opts
-        .test_prefix
-        .split_once("/")
-        .map(|(profile, _)| profile)
-        .unwrap_or(&*opts.test_prefix);
-    let iut_name: &str = &opts.iut;
-    let iut_args = Arc::new(opts.args.clone());
-
-    if let Some(ref config_path) = opts.config {
-        let config_file =
-            BufReader::new(File::open(config_path).context("Failed to open config file")?);
-        let config: Config = serde_json::from_reader(jsonc::Reader::new(config_file))
-            .context("Failed to parse config")?;
-
-        for (ics, value) in config.ics {
-            pts.set_ics(&ics, value);
+    let selectors = opts
+        .selectors
+        .iter()
+        .map(|selector| selector.parse())
+        .collect::<Result<Vec<Selector>>>()?;
+    let excludes = opts
+        .exclude
+        .iter()
+        .map(|selector| selector.parse())
+        .collect::<Result<Vec<Selector>>>()?;
+
+    if opts.jobs > 1 && !opts.hci.supports_jobs() {
+        bail!(
+            "HCI transport {} cannot be shared by parallel jobs",
+            opts.hci
+        );
+    }
+
+    // The transport of the last job has the highest TCP port.
+    opts.hci
+        .for_job(opts.jobs.max(1) - 1, opts.hci_port_stride)?;
+
+    if opts.shard_count == 0 || opts.shard_index >= opts.shard_count {
+        bail!(
+            "Invalid shard {} of {}, the index must be lower than the count",
+            opts.shard_index,
+            opts.shard_count
+        );
+    }
+
+    let mut profile_names: Vec<&str> = Vec::new();
+    for selector in selectors.iter() {
+        if !profile_names.contains(&selector.profile()) {
+            profile_names.push(selector.profile());
+        }
+    }
+
+    // An unknown profile is likely an IUT parameter given before --.
+    let installed = pts.profiles().context("Failed to list the profiles")?;
+    if let Some(unknown) = profile_names
+        .iter()
+        .find(|name| !installed.iter().any(|profile| profile == *name))
+    {
+        bail!(
+            "Profile '{}' not found, the IUT parameters must follow --",
+            unknown
+        );
+    }
+
+    let mut config = Config::load_all(&opts.config)?;
+    // Checked once the extended configs are merged, and before
+    // the --ixit values which could add the missing sections.
+    if let Some(ref config) = config {
+        config.ixit.get("default").context("default IXIT missing")?;
+        for profile_name in profile_names.iter() {
+            config
+                .ixit
+                .get(*profile_name)
+                .with_context(|| format!("IXIT missing for profile '{}'", profile_name))?;
+        }
+    }
+    if !opts.ics.is_empty() || !opts.ixit.is_empty() {
+        config
+            .get_or_insert_with(Config::default)
+            .apply_overrides(&opts.ics, &opts.ixit);
+    }
+
+    let baseline = match opts.baseline {
+        Some(ref path) if opts.update_baseline => Some(Baseline::load_or_default(path)?),
+        Some(ref path) => Some(Baseline::load(path)?),
+        None => None,
+    };
+
+    if let Some(ref config) = config {
+        for (ics, &value) in config.ics.iter() {
+            pts.set_ics(ics, value);
             pts.set_ics(&ics.to_uppercase(), value);
         }
 
-        let pixitx = config.ixit.get("default").context("default IXIT missing")?;
-        for (ixit, value) in pixitx {
+        for (ixit, value) in config.ixit.get("default").into_iter().flatten() {
             pts.set_ixit(ixit, value);
         }
 
-        let pixitx = config
-            .ixit
-            .get(profile_name)
-            .context("IXIT missing for selected profile")?;
-        for (ixit, value) in pixitx {
-            pts.set_ixit(ixit, value);
+        for (profile_name, ics) in config.profile_ics.iter() {
+            for (ics, &value) in ics.iter() {
+                pts.set_profile_ics(profile_name, ics, value);
+                pts.set_profile_ics(profile_name, &ics.to_uppercase(), value);
+            }
         }
 
-        for test in config.skip.unwrap_or_default().into_iter() {
-            skip.insert(test);
+        for profile_name in profile_names.iter() {
+            for (ixit, value) in config.ixit.get(*profile_name).into_iter().flatten() {
+                pts.set_profile_ixit(profile_name, ixit, value);
+            }
+        }
+
+        for test in config.skipped() {
+            skip.insert(test.clone());
         }
     }
 
-    let profile = Arc::new(
-        pts.profile(profile_name)
-            .with_context(|| format!("Profile '{}' not found", profile_name))?,
-    );
+    if let Some(ref command) = opts.command {
+        return commands::run(&pts, command);
+    }
 
-    let tests = profile
-        .tests()
-        .filter(|test| test.starts_with(&opts.test_prefix))
-        .filter(|test| !skip.contains(test))
-        .collect::<Vec<_>>();
+    let profiles = profile_names
+        .iter()
+        .map(|profile_name| {
+            pts.profile(profile_name)
+                .with_context(|| format!("Profile '{}' not found", profile_name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(ref config) = config {
+        let definitions = profiles.iter().map(Definitions::from).collect::<Vec<_>>();
+        let issues = validate::validate(config, &definitions);
+        for issue in issues.iter() {
+            eprintln!("{}", issue);
+        }
+        if issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error || opts.strict_config)
+        {
+            bail!("Invalid config");
+        }
+    }
+
+    let mut tests = Vec::new();
+    for (profile_name, profile) in profile_names.into_iter().zip(profiles.iter()) {
+        tests.extend(
+            profile
+                .tests()
+                .filter(|test| {
+                    selectors.iter().any(|selector| {
+                        selector.profile() == profile_name && selector.matches(test)
+                    })
+                })
+                .filter(|test| !excludes.iter().any(|selector| selector.matches(test)))
+                .filter(|test| !skip.contains(test))
+                .map(|name| TestCase {
+                    profile: profile_name.to_owned(),
+                    name,
+                }),
+        );
+    }
+
+    if opts.shard_count > 1 {
+        let total = tests.len();
+        tests = selector::shard(
+            tests,
+            |test| test.name.as_str(),
+            opts.shard_index,
+            opts.shard_count,
+        );
+        println!(
+            "Shard {} of {}: {} of {} tests",
+            opts.shard_index,
+            opts.shard_count,
+            tests.len(),
+            total
+        );
+    }
+
+    let mut previous = match opts.resume.as_deref().or(opts.rerun_failed.as_deref()) {
+        Some(path) => state::load(path)?,
+        None => HashMap::new(),
+    };
+
+    let mut done = Vec::new();
+    if opts.resume.is_some() {
+        let (finished, remaining): (Vec<_>, Vec<_>) = tests.into_iter().partition(|test| {
+            previous
+                .get(&test.name)
+                .is_some_and(|record| record.verdict != Verdict::None)
+        });
+        println!(
+            "Resuming: {} tests already run, {} remaining",
+            finished.len(),
+            remaining.len()
+        );
+        tests = remaining;
+        done = finished;
+    } else if opts.rerun_failed.is_some() {
+        tests.retain(|test| {
+            previous
+                .get(&test.name)
+                .is_some_and(|record| record.verdict != Verdict::Pass)
+        });
+        println!("Rerunning {} failed tests", tests.len());
+    }
 
-    println!("Tests: {:?}", tests);
+    if let Some(ref config) = config {
+        for test in tests.iter() {
+            for overrides in config.test_overrides(&test.name)? {
+                for (ics, &value) in overrides.ics.iter() {
+                    pts.set_test_ics(&test.name, ics, value);
+                    pts.set_test_ics(&test.name, &ics.to_uppercase(), value);
+                }
+                for (ixit, value) in overrides.ixit.iter() {
+                    pts.set_test_ixit(&test.name, ixit, value);
+                }
+            }
+        }
+    }
+
+    println!(
+        "Tests: {:?}",
+        tests.iter().map(|test| &test.name).collect::<Vec<_>>()
+    );
     if opts.list {
         return Ok(());
     }
@@ -234,92 +451,53 @@ fn main() -> Result<()> {
     pyo3::prepare_freethreaded_python();
 
     let ctrlc = CtrlC::new().context("Failed to create Ctrl+C handler")?;
-    let fail_fast = opts.fail_fast;
-    let inactivity_timeout = opts.inactivity_timeout;
-
-    block_on(async move {
-        let stream = stream::iter(tests.clone()).then(|test| {
-            let profile = profile.clone();
-            let iut_args = iut_args.clone();
-
-            async move {
-                let iut = Arc::new(PythonIUT::new(iut_name, &iut_args, &test)?);
-                let timeout = async_io::Timer::after(Duration::from_secs(inactivity_timeout));
-
-                let addr = {
-                    let iut = iut.clone();
-
-                    future::or(
-                        unblock(move || -> Result<BdAddr> {
-                            println!("Resetting IUT ...");
-                            iut.enter()?;
-
-                            println!("Reading local address ...");
-                            Ok(BdAddr::new(
-                                iut.address()?
-                                    .try_into()
-                                    .map_err(|_| Error::msg("Invalid address size"))?,
-                            ))
-                        }),
-                        async {
-                            timeout.await;
-                            anyhow::bail!("Timeout in IUT initialization")
-                        },
-                    )
-                    .await?
-                };
-
-                println!("Local address: {}", addr);
-                let events = profile
-                    .run_test(
-                        &test,
-                        addr,
-                        connect_to_hci,
-                        move |i| {
-                            let iut = iut.clone();
-                            unblock(move || iut.interact(i))
-                        },
-                        Some("/tmp/audiodata"),
-                        inactivity_timeout,
-                    )
-                    .await;
-
-                let events = map_with_stack(events, |result| {
-                    result.map(|(event, stack)| {
-                        logger::print(&mut stdout(), &event, stack).unwrap();
-                        event
-                    })
-                });
 
-                let result: Result<test::TestResult> = final_verdict(events)
-                    .await
-                    .context("Runtime Error")
-                    .try_into();
+    let mut state = match (&opts.state, &opts.resume, &opts.rerun_failed) {
+        (Some(path), _, _) => Some(State::create(path)?),
+        (_, Some(path), _) | (_, _, Some(path)) => Some(State::append(path)?),
+        _ => None,
+    };
+
+    let (results, error) = runner::run(&pts, &tests, &cache, &opts, ctrlc, state.as_mut())?;
+
+    // The results of a resumed run include the ones of the previous runs.
+    let results = done
+        .into_iter()
+        .filter_map(|test| previous.remove(&test.name))
+        .map(TestExecution::from)
+        .chain(results)
+        .collect::<Vec<_>>();
 
-                // The configuration TSPX_delete_link_key should normally
-                // force the PTS to remove the link key database;
-                // however OPP tests do not properly apply this configuration
-                // resulting in test failures.
-                profile.delete_link_key();
+    test::report(&results);
 
-                result
+    if let Some(ref path) = opts.junit {
+        let mut file = File::create(path).context("Failed to create JUnit report")?;
+        junit::write(&mut file, &results).context("Failed to write JUnit report")?;
+    }
+
+    // The results of the tests which ended are reported even when a job failed.
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    if let (Some(mut baseline), Some(path)) = (baseline, opts.baseline.as_deref()) {
+        let mismatches = baseline.compare(&results);
+        baseline::report(&mismatches);
+
+        if opts.update_baseline {
+            baseline.update(&results);
+            baseline.save(path)?;
+            println!("Baseline {} updated", path.display());
+        } else {
+            let unexpected = mismatches
+                .iter()
+                .filter(|mismatch| mismatch.is_regression() || mismatch.is_unexpected_pass())
+                .count();
+            if unexpected > 0 {
+                bail!("{} results differ from the baseline", unexpected);
             }
-        });
-        pin!(stream);
-        let results: Vec<_> = take_until(abortable(stream, ctrlc), |result| {
-            fail_fast && !matches!(result, Ok(test::TestResult::Pass))
-        })
-        .chain(stream::repeat_with(|| {
-            // Provide a None result to all the test that
-            // have not been executed (because of a Ctrl-C)
-            Ok(test::TestResult::None)
-        }))
-        .zip(stream::iter(tests.into_iter()))
-        .map(|(result, name)| result.map(|result| test::TestExecution { name, result }))
-        .try_collect()
-        .await?;
-
-        test::report(results);
-        Ok(())
-    })
+        }
+    }
+
+    Ok(())
 }
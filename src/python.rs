@@ -22,7 +22,7 @@ use pyo3::{
     PyErr, PyObject, PyResult, Python,
 };
 
-use super::Interaction;
+use libpts::Interaction;
 
 #[derive(Debug)]
 pub struct Error(PyErr);
@@ -51,8 +51,9 @@ pub struct PythonIUT(PyObject);
 /// Bind to an IUT python object with the following template:
 ///
 /// class IUT:
-///    def __init__(self, test: str, args: List[str], **kwargs):
-///        """Initialize the instance manager."""
+///    def __init__(self, test: str, args: List[str], job: int, **kwargs):
+///        """Initialize the instance manager.
+///           `job` is the index of the parallel job running the test."""
 ///        pass
 ///
 ///    def __enter__(self):
@@ -80,11 +81,12 @@ pub struct PythonIUT(PyObject);
 ///        pass
 ///
 impl PythonIUT {
-    pub fn new(name: &str, args: &Vec<String>, test: &str) -> Result<Self, Error> {
+    pub fn new(name: &str, args: &Vec<String>, test: &str, job: usize) -> Result<Self, Error> {
         Python::with_gil(|py| -> PyResult<Self> {
             let kwargs = PyDict::new(py);
             kwargs.set_item("test", test)?;
             kwargs.set_item("args", args)?;
+            kwargs.set_item("job", job)?;
             PyModule::import(py, name)?
                 .getattr("IUT")?
                 .call((), Some(&kwargs))
@@ -123,7 +125,7 @@ impl PythonIUT {
         .map_err(Error)
     }
 
-    pub fn interact(&self, interaction: Interaction) -> Result<String, Error> {
+    pub fn interact(&self, interaction: &Interaction) -> Result<String, Error> {
         Python::with_gil(|py| -> PyResult<String> {
             let (addr, style, id, profile, test, description) = interaction.explode();
             let style = format!("{:?}", style);
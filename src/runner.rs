@@ -0,0 +1,458 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::task::Poll;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Error, Result};
+use libpts::{final_verdict, logger, map_with_stack, BdAddr, Event, EventKind, Profile, HCI, PTS};
+
+use futures_lite::{future, io, stream, StreamExt};
+
+use async_channel::{Receiver, Sender};
+
+use async_io::block_on;
+
+use async_ctrlc::CtrlC;
+
+use blocking::unblock;
+
+use crate::artifacts::{self, Artifacts};
+use crate::btsnoop::{Capture, Direction, Tap};
+use crate::fault::{self, Fault};
+use crate::hci::{Transport, Writer};
+use crate::python::PythonIUT;
+use crate::state::State;
+use crate::test::{Attempt, TestCase, TestExecution, TestResult};
+use crate::Opts;
+
+/// Environment of one of the parallel runners.
+struct Job {
+    index: usize,
+    hci: Transport,
+    audio_output_path: String,
+}
+
+impl Job {
+    fn new(index: usize, opts: &Opts) -> Result<Self> {
+        Ok(Job {
+            index,
+            hci: opts.hci.for_job(index, opts.hci_port_stride)?,
+            audio_output_path: if index == 0 {
+                String::from("/tmp/audiodata")
+            } else {
+                format!("/tmp/audiodata-{}", index)
+            },
+        })
+    }
+}
+
+async fn connect_to_hci(
+    port: HCI,
+    hci: Transport,
+    job: usize,
+    capture: Option<Capture>,
+    rules: &[fault::Rule],
+    faults: Sender<Fault>,
+) -> std::io::Result<()> {
+    let (controllerrx, controllertx) = hci.connect(job).await?;
+
+    let (hcirx, hcitx) = io::split(port);
+
+    // Record the packets as written, once the faults are injected.
+    let (controllertx, hcitx): (Writer, Writer) = match capture {
+        Some(capture) => (
+            Box::new(Tap::new(controllertx, Direction::Sent, capture.clone())),
+            Box::new(Tap::new(hcitx, Direction::Received, capture)),
+        ),
+        None => (controllertx, Box::new(hcitx)),
+    };
+
+    if rules.is_empty() {
+        future::or(io::copy(hcirx, controllertx), io::copy(controllerrx, hcitx)).await?;
+    } else {
+        future::or(
+            fault::copy(hcirx, controllertx, Direction::Sent, rules, faults.clone()),
+            fault::copy(controllerrx, hcitx, Direction::Received, rules, faults),
+        )
+        .await?;
+    }
+
+    println!("HCI ended");
+
+    Ok(())
+}
+
+async fn execute(
+    profile: &Profile<'_>,
+    job: &Job,
+    test: String,
+    retry: usize,
+    opts: &Opts,
+) -> Result<TestExecution> {
+    let mut artifacts = opts
+        .artifacts
+        .as_deref()
+        .map(|root| Artifacts::create(artifacts::directory(root, &test, retry)))
+        .transpose()
+        .context("Failed to create artifacts directory")?;
+    let mmi = artifacts.as_ref().map(Artifacts::mmi);
+    let capture = artifacts.as_ref().map(Artifacts::capture);
+    let mut server_output = artifacts
+        .as_ref()
+        .map(Artifacts::server_output)
+        .transpose()?;
+
+    let started = Instant::now();
+    let inactivity_timeout = opts.inactivity_timeout;
+    let iut = Arc::new(PythonIUT::new(&opts.iut, &opts.args, &test, job.index)?);
+    let timeout = async_io::Timer::after(Duration::from_secs(inactivity_timeout));
+
+    let addr = {
+        let iut = iut.clone();
+
+        future::or(
+            unblock(move || -> Result<BdAddr> {
+                println!("Resetting IUT ...");
+                iut.enter()?;
+
+                println!("Reading local address ...");
+                Ok(BdAddr::new(
+                    iut.address()?
+                        .try_into()
+                        .map_err(|_| Error::msg("Invalid address size"))?,
+                ))
+            }),
+            async {
+                timeout.await;
+                anyhow::bail!("Timeout in IUT initialization")
+            },
+        )
+        .await?
+    };
+
+    println!("Local address: {}", addr);
+    let (hci, job_index) = (job.hci.clone(), job.index);
+    let (faults_tx, mut faults) = async_channel::unbounded();
+    let events = profile
+        .run_test(
+            &test,
+            addr,
+            move |port| {
+                connect_to_hci(
+                    port,
+                    hci.clone(),
+                    job_index,
+                    capture.clone(),
+                    &opts.hci_fault,
+                    faults_tx.clone(),
+                )
+            },
+            move |i| {
+                let iut = iut.clone();
+                let mmi = mmi.clone();
+                unblock(move || {
+                    let answer = iut.interact(&i);
+                    if let Some(mmi) = mmi {
+                        mmi.record(&i, &answer);
+                    }
+                    answer
+                })
+            },
+            move |line| {
+                if let Some(ref mut output) = server_output {
+                    let _ = writeln!(output, "{}", line);
+                }
+            },
+            Some(&job.audio_output_path),
+            inactivity_timeout,
+        )
+        .await;
+
+    // Report the injected faults along the events of the test.
+    let mut events = Box::pin(events);
+    let events = stream::poll_fn(move |cx| {
+        if let Poll::Ready(Some(fault)) = faults.poll_next(cx) {
+            return Poll::Ready(Some(Ok(Event {
+                kind: EventKind::Fault(fault.direction),
+                time: None,
+                number: None,
+                name: fault.to_string(),
+                values: None,
+            })));
+        }
+        events.poll_next(cx)
+    });
+
+    // When several jobs are running, the log of each test is
+    // printed at once when it ends so that logs are not interleaved.
+    let buffered = opts.jobs > 1;
+    let capture_log = opts.junit.is_some();
+    let mut log = Vec::new();
+    let events = map_with_stack(events, |result| {
+        result.map(|(event, stack)| {
+            if buffered || capture_log {
+                logger::print(&mut log, &event, stack).unwrap();
+            }
+            if !buffered {
+                logger::print(&mut stdout(), &event, stack).unwrap();
+            }
+            if let Some(ref mut writer) = artifacts {
+                // A full disk should not abort the test, only its artifacts.
+                if let Err(e) = writer.event(&event, stack) {
+                    eprintln!("Failed to write the events of {}: {}", test, e);
+                    artifacts = None;
+                }
+            }
+            event
+        })
+    });
+
+    let result: TestResult = final_verdict(events)
+        .await
+        .context("Runtime Error")
+        .try_into()?;
+
+    if buffered {
+        stdout().lock().write_all(&log)?;
+    }
+
+    // The configuration TSPX_delete_link_key should normally
+    // force the PTS to remove the link key database;
+    // however OPP tests do not properly apply this configuration
+    // resulting in test failures.
+    profile.delete_link_key();
+
+    if let Some(artifacts) = artifacts {
+        artifacts
+            .verdict(&test, &result, started.elapsed())
+            .context("Failed to write artifacts")?;
+    }
+
+    Ok(TestExecution {
+        profile: profile.name().to_owned(),
+        groups: test_groups(profile, &test),
+        name: test,
+        result,
+        duration: started.elapsed(),
+        log: if capture_log {
+            String::from_utf8_lossy(&log).into_owned()
+        } else {
+            String::new()
+        },
+        retries: Vec::new(),
+    })
+}
+
+// Run a test until it passes, at most `opts.retries` more times.
+// Each attempt runs in a fresh server process.
+async fn execute_with_retries(
+    profile: &Profile<'_>,
+    job: &Job,
+    test: String,
+    opts: &Opts,
+) -> Result<TestExecution> {
+    let mut retries = Vec::new();
+    loop {
+        let mut execution = execute(profile, job, test.clone(), retries.len(), opts).await?;
+
+        if matches!(execution.result, TestResult::Pass) && !retries.is_empty() {
+            execution.result = TestResult::Flaky;
+        }
+
+        if execution.result.is_success() || retries.len() >= opts.retries {
+            execution.retries = retries;
+            return Ok(execution);
+        }
+
+        println!(
+            "Retrying {} (attempt {} of {})",
+            test,
+            retries.len() + 2,
+            opts.retries + 1
+        );
+
+        retries.push(Attempt {
+            result: execution.result,
+            duration: execution.duration,
+            log: execution.log,
+        });
+    }
+}
+
+fn test_groups(profile: &Profile<'_>, test: &str) -> Vec<String> {
+    profile
+        .test_groups(test)
+        .unwrap_or_default()
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+// Load each of the profiles containing the tests.
+fn profiles<'pts>(pts: &'pts PTS, tests: &[TestCase]) -> Result<HashMap<String, Profile<'pts>>> {
+    let mut profiles = HashMap::new();
+    for test in tests {
+        if !profiles.contains_key(&test.profile) {
+            let profile = pts
+                .profile(&test.profile)
+                .with_context(|| format!("Profile '{}' not found", test.profile))?;
+            profiles.insert(test.profile.clone(), profile);
+        }
+    }
+    Ok(profiles)
+}
+
+async fn worker(
+    profiles: &HashMap<String, Profile<'_>>,
+    job: Job,
+    opts: &Opts,
+    tests: Receiver<(usize, TestCase)>,
+    results: Sender<(usize, TestExecution)>,
+) -> Result<()> {
+    while let Ok((index, test)) = tests.recv().await {
+        let profile = &profiles[&test.profile];
+        let execution = execute_with_retries(profile, &job, test.name, opts).await?;
+        if results.send((index, execution)).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Run the tests on `opts.jobs` independent PTS environments.
+/// The first job runs on `pts` itself, the others on copies
+/// of its prefix created next to `cache`. Each result is saved
+/// to `state` as soon as the test ends. When a job fails, the
+/// others are stopped and its error is returned along with
+/// the results of the tests which already ended.
+pub fn run(
+    pts: &PTS,
+    tests: &[TestCase],
+    cache: &Path,
+    opts: &Opts,
+    ctrlc: CtrlC,
+    mut state: Option<&mut State>,
+) -> Result<(Vec<TestExecution>, Option<Error>)> {
+    let profiles = profiles(pts, tests)?;
+
+    let (queue, pending) = async_channel::unbounded();
+    for test in tests.iter().cloned().enumerate() {
+        queue.try_send(test).unwrap();
+    }
+    queue.close();
+
+    // The abort channel never carries any message, closing
+    // it stops all the jobs.
+    let (abort, aborted) = async_channel::bounded::<()>(1);
+    let (sender, receiver) = async_channel::unbounded();
+
+    let mut results: Vec<Option<TestExecution>> = tests.iter().map(|_| None).collect();
+
+    let error = thread::scope(|scope| {
+        let handles = (0..opts.jobs.max(1))
+            .map(|index| {
+                let pending = pending.clone();
+                let sender = sender.clone();
+                let abort = abort.clone();
+                let aborted = aborted.clone();
+
+                scope.spawn(move || {
+                    let result = (|| {
+                        let job = Job::new(index, opts)?;
+                        let clone;
+                        let pts = if index == 0 {
+                            pts
+                        } else {
+                            let directory =
+                                PathBuf::from(format!("{}-job{}", cache.display(), index));
+                            println!("Cloning PTS to {}", directory.display());
+                            clone = pts.clone_to(directory).context("Failed to clone PTS")?;
+                            &clone
+                        };
+                        let profiles = self::profiles(pts, tests)?;
+
+                        block_on(future::or(
+                            worker(&profiles, job, opts, pending, sender),
+                            async {
+                                let _ = aborted.recv().await;
+                                Ok(())
+                            },
+                        ))
+                    })();
+
+                    if result.is_err() {
+                        abort.close();
+                    }
+                    result
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Only the jobs should hold the results sender, so
+        // that the channel is closed when they are all done.
+        drop(sender);
+
+        block_on(future::or(
+            async {
+                while let Ok((index, execution)) = receiver.recv().await {
+                    if opts.fail_fast && !execution.result.is_success() {
+                        abort.close();
+                    }
+                    if let Some(ref mut state) = state {
+                        if let Err(e) = state.record(&execution) {
+                            eprintln!("Failed to save the result of {}: {}", execution.name, e);
+                        }
+                    }
+                    results[index] = Some(execution);
+                }
+            },
+            async {
+                ctrlc.await;
+                abort.close();
+                future::pending().await
+            },
+        ));
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().expect("Job panicked"))
+            .err()
+    });
+
+    // Provide an Aborted result to all the test that have not
+    // been executed (because of a Ctrl-C or of a failed job)
+    let results = results
+        .into_iter()
+        .zip(tests)
+        .map(|(execution, test)| {
+            execution.unwrap_or_else(|| TestExecution {
+                profile: test.profile.clone(),
+                groups: test_groups(&profiles[&test.profile], &test.name),
+                name: test.name.clone(),
+                result: TestResult::Aborted,
+                duration: Duration::ZERO,
+                log: String::new(),
+                retries: Vec::new(),
+            })
+        })
+        .collect();
+    Ok((results, error))
+}
@@ -0,0 +1,168 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+use glob::Pattern;
+use regex::Regex;
+
+/// Selection of tests by name.
+///
+/// - `re:<regex>` selects the tests matching the regular expression,
+///   which must start with `^PROFILE/`.
+/// - A selector containing `*`, `?` or `[` is a glob matched against the
+///   whole test name, `*` also matches `/`.
+/// - Any other selector selects the tests starting with it.
+#[derive(Debug)]
+pub enum Selector {
+    Prefix(String),
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl Selector {
+    pub fn matches(&self, test: &str) -> bool {
+        match self {
+            Selector::Prefix(prefix) => test.starts_with(prefix.as_str()),
+            Selector::Glob(pattern) => pattern.matches(test),
+            Selector::Regex(regex) => regex.is_match(test),
+        }
+    }
+
+    /// Name of the profile containing the selected tests.
+    pub fn profile(&self) -> &str {
+        let selector = match self {
+            Selector::Prefix(prefix) => prefix.as_str(),
+            Selector::Glob(pattern) => pattern.as_str(),
+            Selector::Regex(regex) => &regex.as_str()[1..],
+        };
+        selector
+            .split_once('/')
+            .map(|(profile, _)| profile)
+            .unwrap_or(selector)
+    }
+}
+
+/// Keep the tests of the shard `index` out of `count`. The tests are
+/// assigned round-robin in the order of their sorted names, so that the
+/// shards are disjoint and of the same size whatever the selection order.
+pub fn shard<T>(tests: Vec<T>, name: impl Fn(&T) -> &str, index: usize, count: usize) -> Vec<T> {
+    let mut sorted = tests.iter().map(&name).collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let selected = sorted
+        .into_iter()
+        .skip(index)
+        .step_by(count)
+        .map(str::to_owned)
+        .collect::<HashSet<_>>();
+
+    tests
+        .into_iter()
+        .filter(|test| selected.contains(name(test)))
+        .collect()
+}
+
+fn is_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(selector: &str) -> Result<Self> {
+        let parsed = if let Some(regex) = selector.strip_prefix("re:") {
+            if !regex.starts_with('^') {
+                bail!("Regex selector '{}' must start with ^PROFILE/", selector);
+            }
+            Selector::Regex(
+                Regex::new(regex).with_context(|| format!("Invalid regex '{}'", regex))?,
+            )
+        } else if selector.contains(['*', '?', '[']) {
+            Selector::Glob(
+                Pattern::new(selector).with_context(|| format!("Invalid glob '{}'", selector))?,
+            )
+        } else {
+            Selector::Prefix(selector.to_owned())
+        };
+
+        if !is_profile_name(parsed.profile()) {
+            bail!(
+                "Selector '{}' must start with the profile name followed by '/'",
+                selector
+            );
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{shard, Selector};
+
+    fn selector(selector: &str) -> Selector {
+        selector.parse().unwrap()
+    }
+
+    #[test]
+    fn test_prefix() {
+        let selector = selector("A2DP/SRC");
+        assert_eq!(selector.profile(), "A2DP");
+        assert!(selector.matches("A2DP/SRC/AS/BV-01-I"));
+        assert!(!selector.matches("A2DP/SNK/AS/BV-01-I"));
+        assert_eq!(self::selector("AVRCP").profile(), "AVRCP");
+    }
+
+    #[test]
+    fn test_glob() {
+        let selector = selector("AVDTP/SRC/ACP/SIG/SMG/BV-0[5-9]-C");
+        assert_eq!(selector.profile(), "AVDTP");
+        assert!(selector.matches("AVDTP/SRC/ACP/SIG/SMG/BV-05-C"));
+        assert!(!selector.matches("AVDTP/SRC/ACP/SIG/SMG/BV-11-C"));
+        assert!(self::selector("A2DP/SRC/*").matches("A2DP/SRC/AS/BV-01-I"));
+    }
+
+    #[test]
+    fn test_regex() {
+        let selector = selector("re:^HFP/AG/SLC/");
+        assert_eq!(selector.profile(), "HFP");
+        assert!(selector.matches("HFP/AG/SLC/BV-01-C"));
+        assert!(!selector.matches("HFP/HF/SLC/BV-01-C"));
+    }
+
+    #[test]
+    fn test_shard() {
+        let tests = vec!["HFP/AG/E", "HFP/AG/B", "HFP/AG/D", "HFP/AG/A", "HFP/AG/C"];
+
+        // Tests are kept in their selection order
+        assert_eq!(
+            shard(tests.clone(), |test| *test, 0, 2),
+            ["HFP/AG/E", "HFP/AG/A", "HFP/AG/C"]
+        );
+        assert_eq!(
+            shard(tests.clone(), |test| *test, 1, 2),
+            ["HFP/AG/B", "HFP/AG/D"]
+        );
+        assert_eq!(shard(tests.clone(), |test| *test, 0, 1), tests);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("re:HFP/AG".parse::<Selector>().is_err());
+        assert!("*/SRC".parse::<Selector>().is_err());
+        assert!("A2DP/SRC/[".parse::<Selector>().is_err());
+    }
+}
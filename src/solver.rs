@@ -0,0 +1,340 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use libpts::Expression;
+use serde::Serialize;
+
+/// What the solver may do with an ICS item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Item {
+    /// Set by the config.
+    Config(bool),
+    /// Mandatory row of the PICSX, it keeps its default.
+    Mandatory(bool),
+    /// Not in the PICSX, it never has a value.
+    Undefined,
+    /// Free to change from its PICSX default.
+    Free(bool),
+}
+
+impl Item {
+    fn fixed(self) -> Option<Option<bool>> {
+        match self {
+            Item::Config(value) | Item::Mandatory(value) => Some(Some(value)),
+            Item::Undefined => Some(None),
+            Item::Free(_) => None,
+        }
+    }
+}
+
+/// Value to declare for an ICS item, different from its default.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Assignment {
+    pub name: String,
+    pub value: bool,
+}
+
+/// Target test which cannot be enabled.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Unsatisfiable {
+    pub test: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Solution {
+    /// Smallest set of free items to change from their default
+    /// so that all the satisfiable targets are enabled.
+    pub assignments: Vec<Assignment>,
+    pub unsatisfiable: Vec<Unsatisfiable>,
+}
+
+// Branch and bound over the free items of the targets, trying their
+// default value first and pruning as soon as a target is false or
+// the changes are not fewer than the ones of the best assignment.
+struct Search<'a> {
+    targets: &'a [&'a Expression],
+    fixed: HashMap<&'a str, Option<bool>>,
+    free: Vec<(&'a str, bool)>,
+    values: Vec<Option<bool>>,
+    best: Option<(usize, Vec<bool>)>,
+}
+
+impl<'a> Search<'a> {
+    fn new(targets: &'a [&'a Expression], item: &dyn Fn(&str) -> Item) -> Self {
+        let mut fixed = HashMap::new();
+        let mut free = Vec::new();
+        for target in targets {
+            for name in target.items() {
+                if fixed.contains_key(name) || free.iter().any(|(free, _)| *free == name) {
+                    continue;
+                }
+                match item(name) {
+                    Item::Free(default) => free.push((name, default)),
+                    item => {
+                        fixed.insert(name, item.fixed().flatten());
+                    }
+                }
+            }
+        }
+        let values = vec![None; free.len()];
+        Search {
+            targets,
+            fixed,
+            free,
+            values,
+            best: None,
+        }
+    }
+
+    fn value(&self, name: &str) -> Option<bool> {
+        match self.fixed.get(name) {
+            Some(value) => *value,
+            None => self
+                .free
+                .iter()
+                .position(|(free, _)| *free == name)
+                .and_then(|index| self.values[index]),
+        }
+    }
+
+    fn search(&mut self, index: usize, changes: usize) {
+        if self.best.as_ref().is_some_and(|(best, _)| changes >= *best) {
+            return;
+        }
+
+        let mut satisfied = true;
+        for target in self.targets {
+            match target.evaluate(&|name| self.value(name)) {
+                Some(true) => {}
+                Some(false) => return,
+                None => satisfied = false,
+            }
+        }
+        if satisfied {
+            // The items left keep their default
+            let values = self
+                .free
+                .iter()
+                .zip(self.values.iter())
+                .map(|((_, default), value)| value.unwrap_or(*default))
+                .collect();
+            self.best = Some((changes, values));
+            return;
+        }
+        if index == self.free.len() {
+            return;
+        }
+
+        let default = self.free[index].1;
+        for (value, change) in [(default, 0), (!default, 1)] {
+            self.values[index] = Some(value);
+            self.search(index + 1, changes + change);
+        }
+        self.values[index] = None;
+    }
+
+    fn solve(mut self) -> Option<Vec<Assignment>> {
+        // Tests with an item missing from the PICSX are never enabled.
+        if self.fixed.values().any(Option::is_none) {
+            return None;
+        }
+        self.search(0, 0);
+        let (_, values) = self.best?;
+        Some(
+            self.free
+                .iter()
+                .zip(values)
+                .filter(|((_, default), value)| value != default)
+                .map(|((name, _), value)| Assignment {
+                    name: name.to_string(),
+                    value,
+                })
+                .collect(),
+        )
+    }
+}
+
+fn satisfiable(targets: &[&Expression], item: &dyn Fn(&str) -> Item) -> bool {
+    Search::new(targets, item).solve().is_some()
+}
+
+// Why a target cannot be enabled on its own.
+fn reason(expression: &Expression, item: &dyn Fn(&str) -> Item) -> String {
+    let mut undefined = Vec::new();
+    let mut fixed = Vec::new();
+    for name in expression.items() {
+        match item(name) {
+            Item::Undefined => undefined.push(name),
+            Item::Config(value) => fixed.push(format!("{} = {} (config)", name, upper(value))),
+            Item::Mandatory(value) => {
+                fixed.push(format!("{} = {} (mandatory)", name, upper(value)))
+            }
+            Item::Free(_) => {}
+        }
+    }
+
+    let mut reasons = Vec::new();
+    if !undefined.is_empty() {
+        reasons.push(format!("{} not in PICSX", undefined.join(", ")));
+    }
+    if !fixed.is_empty() {
+        reasons.push(format!("false with {}", fixed.join(", ")));
+    }
+    if reasons.is_empty() {
+        String::from("The mapping is never true")
+    } else {
+        let reason = reasons.join(", ");
+        reason[..1].to_uppercase() + &reason[1..]
+    }
+}
+
+fn upper(value: bool) -> String {
+    value.to_string().to_uppercase()
+}
+
+/// Find the smallest change of the free ICS items enabling the targets,
+/// given as test names with their mapping. The targets which cannot be
+/// enabled, on their own or together with the previous ones, are left
+/// out of the assignment and reported with the cause of the conflict.
+pub fn solve(targets: &[(&str, &str)], item: &dyn Fn(&str) -> Item) -> Solution {
+    let mut solution = Solution::default();
+    let mut accepted: Vec<(&str, Expression)> = Vec::new();
+
+    for &(test, mapping) in targets {
+        let expression = match Expression::parse(mapping) {
+            Ok(expression) => expression,
+            Err(e) => {
+                solution.unsatisfiable.push(Unsatisfiable {
+                    test: test.to_owned(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let reason = if !satisfiable(&[&expression], item) {
+            Some(reason(&expression, item))
+        } else {
+            let mut expressions: Vec<_> = accepted.iter().map(|(_, e)| e).collect();
+            expressions.push(&expression);
+            if satisfiable(&expressions, item) {
+                None
+            } else {
+                let conflict = accepted
+                    .iter()
+                    .find(|(_, other)| !satisfiable(&[other, &expression], item));
+                Some(match conflict {
+                    Some((other, _)) => format!("Conflicts with {}", other),
+                    None => String::from("Conflicts with the other targets"),
+                })
+            }
+        };
+
+        match reason {
+            Some(reason) => solution.unsatisfiable.push(Unsatisfiable {
+                test: test.to_owned(),
+                reason,
+            }),
+            None => accepted.push((test, expression)),
+        }
+    }
+
+    let expressions: Vec<_> = accepted.iter().map(|(_, e)| e).collect();
+    solution.assignments = Search::new(&expressions, item).solve().unwrap_or_default();
+    solution
+}
+
+#[cfg(test)]
+mod test {
+    use super::{solve, Assignment, Item, Unsatisfiable};
+
+    fn item(name: &str) -> Item {
+        match name {
+            "TSPC_HFP_1_1" => Item::Config(true),
+            "TSPC_HFP_1_2" => Item::Config(false),
+            "TSPC_HFP_2_1" => Item::Mandatory(true),
+            "TSPC_HFP_3_1" => Item::Free(false),
+            "TSPC_HFP_3_2" => Item::Free(false),
+            "TSPC_HFP_3_3" => Item::Free(true),
+            _ => Item::Undefined,
+        }
+    }
+
+    fn assignment(name: &str, value: bool) -> Assignment {
+        Assignment {
+            name: name.to_owned(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_minimal() {
+        let solution = solve(
+            &[
+                ("HFP/AG/SLC/BV-01-C", "TSPC_HFP_1_1 AND TSPC_HFP_3_1"),
+                (
+                    "HFP/AG/SLC/BV-02-C",
+                    "(TSPC_HFP_3_1 OR TSPC_HFP_3_2) AND TSPC_HFP_3_3",
+                ),
+                ("HFP/AG/SLC/BV-03-C", "TSPC_HFP_2_1 AND NOT TSPC_HFP_3_3"),
+            ],
+            &item,
+        );
+        // TSPC_HFP_3_2 keeps its default, and the third target
+        // needs TSPC_HFP_3_3 FALSE while the second needs it TRUE
+        assert_eq!(solution.assignments, [assignment("TSPC_HFP_3_1", true)]);
+        assert_eq!(
+            solution.unsatisfiable,
+            [Unsatisfiable {
+                test: String::from("HFP/AG/SLC/BV-03-C"),
+                reason: String::from("Conflicts with HFP/AG/SLC/BV-02-C"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unsatisfiable() {
+        let reasons = |mapping| {
+            solve(&[("HFP/AG/SLC/BV-01-C", mapping)], &item)
+                .unsatisfiable
+                .into_iter()
+                .map(|unsatisfiable| unsatisfiable.reason)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            reasons("TSPC_HFP_1_2 OR NOT TSPC_HFP_2_1"),
+            ["False with TSPC_HFP_1_2 = FALSE (config), TSPC_HFP_2_1 = TRUE (mandatory)"]
+        );
+        assert_eq!(
+            reasons("TSPC_HFP_3_1 AND TSPC_HFP_9_9"),
+            ["TSPC_HFP_9_9 not in PICSX"]
+        );
+        assert_eq!(
+            reasons("TSPC_HFP_3_1 AND NOT TSPC_HFP_3_1"),
+            ["The mapping is never true"]
+        );
+        assert_eq!(
+            reasons("TSPC_HFP_3_1 AND"),
+            ["Invalid mapping at column 17 (unexpected end of mapping)"]
+        );
+        assert_eq!(
+            reasons("TSPC_HFP_3_2 OR TSPC_HFP_9_9"),
+            ["TSPC_HFP_9_9 not in PICSX"]
+        );
+    }
+}
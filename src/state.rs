@@ -0,0 +1,206 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::baseline::Verdict;
+use crate::test::{TestExecution, TestResult};
+
+/// Result of a test, as saved in the state file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub profile: String,
+    pub groups: Vec<String>,
+    pub name: String,
+    pub verdict: Verdict,
+    pub duration: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<&TestExecution> for Record {
+    fn from(execution: &TestExecution) -> Self {
+        Record {
+            profile: execution.profile.clone(),
+            groups: execution.groups.clone(),
+            name: execution.name.clone(),
+            verdict: Verdict::from(&execution.result),
+            duration: execution.total_duration().as_secs_f64(),
+            error: match execution.result {
+                TestResult::Error(ref e) => Some(format!("{:#}", e)),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl From<Record> for TestExecution {
+    fn from(record: Record) -> Self {
+        TestExecution {
+            profile: record.profile,
+            groups: record.groups,
+            name: record.name,
+            result: match record.verdict {
+                Verdict::Pass => TestResult::Pass,
+                Verdict::Fail => TestResult::Fail,
+                Verdict::Inconc => TestResult::Inconclusive,
+                Verdict::None => TestResult::None,
+                Verdict::Error => TestResult::Error(anyhow!(record.error.unwrap_or_default())),
+            },
+            duration: Duration::from_secs_f64(record.duration),
+            log: String::new(),
+            retries: Vec::new(),
+        }
+    }
+}
+
+/// Load the last result of each test of a state file.
+pub fn load(path: &Path) -> Result<HashMap<String, Record>> {
+    let file = BufReader::new(File::open(path).context("Failed to open state file")?);
+    let mut records = HashMap::new();
+    for (number, line) in file.lines().enumerate() {
+        let line = line.context("Failed to read state file")?;
+        // The last line is incomplete when the run was killed
+        // while writing it.
+        let record: Record = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(e) if e.is_eof() => break,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Invalid state at line {}", number + 1))
+            }
+        };
+        records.insert(record.name.clone(), record);
+    }
+    Ok(records)
+}
+
+/// State file, with one line appended as soon as a test ends,
+/// so that the results are kept when the run is interrupted.
+pub struct State(File);
+
+impl State {
+    pub fn create(path: &Path) -> Result<Self> {
+        File::create(path)
+            .map(State)
+            .context("Failed to create state file")
+    }
+
+    /// Open a state file to append the results of a resumed run.
+    /// The incomplete last line of an interrupted run is removed,
+    /// so that the next line starts at the beginning of a line.
+    pub fn append(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .context("Failed to open state file")?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .context("Failed to read state file")?;
+        let length = content
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        file.set_len(length as u64)
+            .context("Failed to truncate state file")?;
+        Ok(State(file))
+    }
+
+    pub fn record(&mut self, execution: &TestExecution) -> io::Result<()> {
+        let mut line = serde_json::to_string(&Record::from(execution))?;
+        line.push('\n');
+        self.0.write_all(line.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load, State};
+    use crate::baseline::Verdict;
+    use crate::test::{TestExecution, TestResult};
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::Duration;
+
+    fn execution(name: &str, result: TestResult) -> TestExecution {
+        TestExecution {
+            profile: String::from("HFP"),
+            groups: vec![String::from("AG")],
+            name: name.to_owned(),
+            result,
+            duration: Duration::from_millis(1500),
+            log: String::new(),
+            retries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_state() {
+        let path = std::env::temp_dir().join(format!("pts-bot-state-{}.jsonl", std::process::id()));
+
+        let mut state = State::create(&path).unwrap();
+        state
+            .record(&execution("HFP/AG/SLC/BV-01-C", TestResult::Fail))
+            .unwrap();
+        state
+            .record(&execution(
+                "HFP/AG/SLC/BV-02-C",
+                TestResult::Error(anyhow::anyhow!("Timeout").context("Runtime Error")),
+            ))
+            .unwrap();
+        drop(state);
+
+        let mut state = State::append(&path).unwrap();
+        state
+            .record(&execution("HFP/AG/SLC/BV-01-C", TestResult::Pass))
+            .unwrap();
+        drop(state);
+
+        // Interrupted while writing the last line
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"profile\":\"HFP\",\"gro").unwrap();
+
+        let mut records = load(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records["HFP/AG/SLC/BV-01-C"].verdict, Verdict::Pass);
+
+        // Resumed after the interruption
+        let mut state = State::append(&path).unwrap();
+        state
+            .record(&execution("HFP/AG/SLC/BV-03-C", TestResult::Inconclusive))
+            .unwrap();
+        drop(state);
+
+        let reloaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded.len(), 3);
+        assert_eq!(reloaded["HFP/AG/SLC/BV-03-C"].verdict, Verdict::Inconc);
+
+        let error = TestExecution::from(records.remove("HFP/AG/SLC/BV-02-C").unwrap());
+        assert_eq!(error.groups, ["AG"]);
+        assert_eq!(error.duration, Duration::from_millis(1500));
+        match error.result {
+            TestResult::Error(e) => assert_eq!(e.to_string(), "Runtime Error: Timeout"),
+            _ => panic!("Expected an error"),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{self, Write};
+
+use libpts::{PicsRow, PixitRow};
+
+use crate::commands::single_line;
+
+/// ICS and IXIT definitions of a profile.
+pub struct Definitions<'a> {
+    pub name: &'a str,
+    pub pics: Vec<&'a PicsRow>,
+    pub pixit: Vec<&'a PixitRow>,
+}
+
+fn string(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+// Write the entries of a JSON object, with the comment lines before
+// each of them and commas between them.
+fn entries(
+    to: &mut impl Write,
+    entries: &[(Vec<String>, String)],
+    indent: usize,
+) -> io::Result<()> {
+    for (index, (comments, entry)) in entries.iter().enumerate() {
+        for comment in comments {
+            writeln!(to, "{:indent$}// {}", "", comment, indent = indent)?;
+        }
+        let comma = if index + 1 < entries.len() { "," } else { "" };
+        writeln!(to, "{:indent$}{}{}", "", entry, comma, indent = indent)?;
+    }
+    Ok(())
+}
+
+/// Write a config with every ICS item and IXIT parameter of the
+/// profiles set to its default value, described in comments.
+pub fn write(to: &mut impl Write, profiles: &[Definitions]) -> io::Result<()> {
+    writeln!(to, "{{")?;
+
+    writeln!(to, "  \"ics\": {{")?;
+    let mut ics = Vec::new();
+    for profile in profiles {
+        for (index, row) in profile.pics.iter().enumerate() {
+            let mut comments = Vec::new();
+            if index == 0 {
+                comments.push(format!("{} ICS", profile.name));
+            }
+            comments.push(single_line(&row.description));
+            if row.mandatory {
+                comments.push(String::from("Mandatory"));
+            }
+            ics.push((comments, format!("{}: {}", string(&row.name), row.value)));
+        }
+    }
+    entries(to, &ics, 4)?;
+    writeln!(to, "  }},")?;
+
+    writeln!(to, "  \"ixit\": {{")?;
+    let mut ixit = vec![(Vec::new(), String::from("\"default\": {}"))];
+    for profile in profiles {
+        let mut section = Vec::new();
+        writeln!(section, "{}: {{", string(profile.name))?;
+        let rows = profile
+            .pixit
+            .iter()
+            .map(|row| {
+                let mut comments = vec![single_line(&row.description)];
+                if let Some(value_type) = row.value_type.first() {
+                    comments.push(format!("Type: {}", value_type));
+                }
+                (
+                    comments,
+                    format!("{}: {}", string(&row.name), string(&row.value)),
+                )
+            })
+            .collect::<Vec<_>>();
+        entries(&mut section, &rows, 6)?;
+        write!(section, "    }}")?;
+        ixit.push((Vec::new(), String::from_utf8(section).unwrap()));
+    }
+    entries(to, &ixit, 4)?;
+    writeln!(to, "  }}")?;
+
+    writeln!(to, "}}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{write, Definitions};
+    use crate::config::Config;
+    use crate::jsonc;
+    use libpts::{PicsRow, PixitRow};
+
+    #[test]
+    fn test_template() {
+        let pics = [
+            PicsRow {
+                name: String::from("TSPC_A2DP_1_1"),
+                description: String::from("Role: Source"),
+                value: true,
+                mandatory: false,
+            },
+            PicsRow {
+                name: String::from("TSPC_A2DP_2_1"),
+                description: String::from("SRC: Initiate\n  connection"),
+                value: false,
+                mandatory: true,
+            },
+        ];
+        let pixit = [PixitRow {
+            name: String::from("TSPX_bd_addr_iut"),
+            description: String::from("Address of the IUT"),
+            value_type: vec![String::from("OCTETSTRING")],
+            value: String::from("000000000000"),
+        }];
+        let profiles = [Definitions {
+            name: "A2DP",
+            pics: pics.iter().collect(),
+            pixit: pixit.iter().collect(),
+        }];
+
+        let mut output = Vec::new();
+        write(&mut output, &profiles).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            r#"{
+  "ics": {
+    // A2DP ICS
+    // Role: Source
+    "TSPC_A2DP_1_1": true,
+    // SRC: Initiate connection
+    // Mandatory
+    "TSPC_A2DP_2_1": false
+  },
+  "ixit": {
+    "default": {},
+    "A2DP": {
+      // Address of the IUT
+      // Type: OCTETSTRING
+      "TSPX_bd_addr_iut": "000000000000"
+    }
+  }
+}
+"#
+        );
+
+        let config: Config = jsonc::from_slice(output.as_bytes()).unwrap();
+        assert!(config.ics["TSPC_A2DP_1_1"]);
+        assert_eq!(config.ixit["A2DP"]["TSPX_bd_addr_iut"], "000000000000");
+    }
+}
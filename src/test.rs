@@ -14,6 +14,7 @@
 
 use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use anyhow::anyhow;
 
@@ -25,6 +26,16 @@ pub enum TestResult {
     Inconclusive,
     None,
     Error(anyhow::Error),
+    /// Passed, but only after being retried.
+    Flaky,
+    /// Not run, or cut short, because the run was interrupted.
+    Aborted,
+}
+
+impl TestResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, TestResult::Pass | TestResult::Flaky)
+    }
 }
 
 impl<S: PartialEq<str> + Debug> TryFrom<Result<Option<S>, anyhow::Error>> for TestResult {
@@ -43,12 +54,40 @@ impl<S: PartialEq<str> + Debug> TryFrom<Result<Option<S>, anyhow::Error>> for Te
     }
 }
 
+/// Test selected to run.
+#[derive(Clone)]
+pub struct TestCase {
+    pub profile: String,
+    pub name: String,
+}
+
 pub struct TestExecution {
+    pub profile: String,
+    /// ETS groups containing the test, outermost first.
+    pub groups: Vec<String>,
     pub name: String,
     pub result: TestResult,
+    pub duration: Duration,
+    /// Event log captured during the run, empty when not requested.
+    pub log: String,
+    /// Unsuccessful attempts which preceded the final one.
+    pub retries: Vec<Attempt>,
+}
+
+impl TestExecution {
+    /// Time spent running the test, including the retries.
+    pub fn total_duration(&self) -> Duration {
+        self.duration + self.retries.iter().map(|attempt| attempt.duration).sum()
+    }
+}
+
+pub struct Attempt {
+    pub result: TestResult,
+    pub duration: Duration,
+    pub log: String,
 }
 
-pub fn report(results: Vec<TestExecution>) {
+pub fn report(results: &[TestExecution]) {
     println!();
     for execution in results.iter() {
         print!("  ");
@@ -57,16 +96,17 @@ pub fn report(results: Vec<TestExecution>) {
             TestResult::Pass => print!(" {}✔{} ", color::Fg(color::Green), style::Reset),
             TestResult::Fail => print!(" {}✘{} ", color::Fg(color::Red), style::Reset),
             TestResult::Inconclusive => print!(" {}?{} ", color::Fg(color::Yellow), style::Reset),
-            TestResult::None => print!(
+            TestResult::None | TestResult::Aborted => print!(
                 "{}{}N/A{}",
                 style::Bold,
                 color::Fg(color::Cyan),
                 style::Reset
             ),
             TestResult::Error(_) => print!(" ☠️  "),
+            TestResult::Flaky => print!(" {}✔{} ", color::Fg(color::Magenta), style::Reset),
         };
 
-        println!(
+        print!(
             "  {}{}{}{}",
             style::Bold,
             color::Fg(color::LightWhite),
@@ -74,6 +114,12 @@ pub fn report(results: Vec<TestExecution>) {
             style::Reset,
         );
 
+        if execution.retries.is_empty() {
+            println!();
+        } else {
+            println!(" ({} attempts)", execution.retries.len() + 1);
+        }
+
         if let TestResult::Error(ref e) = execution.result {
             println!("{:?}", e);
         }
@@ -91,13 +137,18 @@ pub fn report(results: Vec<TestExecution>) {
         .iter()
         .filter(|e| matches!(e.result, TestResult::Inconclusive))
         .count();
+    let flaky = results
+        .iter()
+        .filter(|e| matches!(e.result, TestResult::Flaky))
+        .count();
 
     println!(
-        "\n{}Total{}: {}, {} Success, {} Failed, {} Inconclusive",
+        "\n{}Total{}: {}, {} Success, {} Flaky, {} Failed, {} Inconclusive",
         style::Bold,
         style::Reset,
         total,
         success,
+        flaky,
         failed,
         inconc
     );
@@ -0,0 +1,346 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use libpts::{PicsRow, PixitRow, Profile};
+
+use crate::config::Config;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// ICS and IXIT definitions of a profile.
+pub struct Definitions<'a> {
+    pub profile: &'a str,
+    pub pics: Vec<&'a PicsRow>,
+    pub pixit: Vec<&'a PixitRow>,
+}
+
+impl<'a> From<&'a Profile<'_>> for Definitions<'a> {
+    fn from(profile: &'a Profile<'_>) -> Self {
+        Definitions {
+            profile: profile.name(),
+            pics: profile.pics().collect(),
+            pixit: profile.pixit().collect(),
+        }
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+// Names close enough to `name` to be a probable typo, nearest first.
+fn suggestions<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let name = name.to_uppercase();
+    let threshold = (name.len() / 5).max(2);
+
+    let mut close: Vec<(usize, &str)> = candidates
+        .map(|candidate| (levenshtein(&name, &candidate.to_uppercase()), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    close.sort();
+    close.dedup();
+    close.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+fn unknown<'a>(
+    kind: &str,
+    name: &str,
+    context: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Issue {
+    let suggestions = suggestions(name, candidates);
+    let mut message = format!("Unknown {} '{}' {}", kind, name, context);
+    if !suggestions.is_empty() {
+        message.push_str(", did you mean ");
+        message.push_str(
+            &suggestions
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        message.push('?');
+    }
+    Issue {
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+/// Check that an IXIT value matches the type of its definition.
+pub fn is_valid_value(value_type: &str, value: &str) -> bool {
+    match value_type {
+        "BOOLEAN" => value.eq_ignore_ascii_case("TRUE") || value.eq_ignore_ascii_case("FALSE"),
+        "INTEGER" => {
+            let digits = value.strip_prefix('-').unwrap_or(value);
+            !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        "OCTETSTRING" => value.len() & 1 == 0 && value.chars().all(|c| c.is_ascii_hexdigit()),
+        "HEXSTRING" => value.chars().all(|c| c.is_ascii_hexdigit()),
+        "BITSTRING" => value.chars().all(|c| c == '0' || c == '1'),
+        _ => true,
+    }
+}
+
+fn check_value(row: &PixitRow, value: &str, context: &str) -> Option<Issue> {
+    let value_type = row.value_type.first().map(String::as_str).unwrap_or("");
+    (!is_valid_value(value_type, value)).then(|| Issue {
+        severity: Severity::Error,
+        message: format!(
+            "Invalid value '{}' for IXIT '{}' {}, expected {}",
+            value, row.name, context, value_type
+        ),
+    })
+}
+
+fn check_pics<'a>(
+    definitions: &Definitions,
+    names: impl Iterator<Item = &'a String>,
+    context: &str,
+    issues: &mut Vec<Issue>,
+) {
+    for name in names {
+        if !definitions
+            .pics
+            .iter()
+            .any(|row| row.name.eq_ignore_ascii_case(name))
+        {
+            issues.push(unknown(
+                "ICS",
+                name,
+                context,
+                definitions.pics.iter().map(|row| row.name.as_str()),
+            ));
+        }
+    }
+}
+
+fn check_pixit(
+    definitions: &Definitions,
+    ixit: &HashMap<String, String>,
+    context: &str,
+    issues: &mut Vec<Issue>,
+) {
+    for (name, value) in ixit.iter() {
+        match definitions.pixit.iter().find(|row| &row.name == name) {
+            Some(row) => issues.extend(check_value(row, value, context)),
+            None => issues.push(unknown(
+                "IXIT",
+                name,
+                context,
+                definitions.pixit.iter().map(|row| row.name.as_str()),
+            )),
+        }
+    }
+}
+
+/// Check the config keys and IXIT values against the definitions
+/// of the selected profiles. Entries belonging to other profiles
+/// are not checked.
+pub fn validate(config: &Config, profiles: &[Definitions]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    // Global ICS are checked against the profile named in them,
+    // e.g. TSPC_L2CAP_2_1 against L2CAP.
+    for definitions in profiles {
+        let prefix = format!("TSPC_{}_", definitions.profile.to_uppercase());
+        let names = config
+            .ics
+            .keys()
+            .filter(|name| name.to_uppercase().starts_with(&prefix));
+        let context = format!("for profile {}", definitions.profile);
+        check_pics(definitions, names, &context, &mut issues);
+
+        if let Some(ics) = config.profile_ics.get(definitions.profile) {
+            check_pics(definitions, ics.keys(), &context, &mut issues);
+        }
+    }
+
+    if let Some(default) = config.ixit.get("default") {
+        for (name, value) in default.iter() {
+            let rows = profiles
+                .iter()
+                .flat_map(|definitions| definitions.pixit.iter())
+                .filter(|row| &row.name == name)
+                .collect::<Vec<_>>();
+            if rows.is_empty() && !profiles.is_empty() {
+                issues.push(unknown(
+                    "IXIT",
+                    name,
+                    "in default IXIT",
+                    profiles
+                        .iter()
+                        .flat_map(|definitions| definitions.pixit.iter())
+                        .map(|row| row.name.as_str()),
+                ));
+            }
+            if let Some(issue) = rows
+                .into_iter()
+                .find_map(|row| check_value(row, value, "in default IXIT"))
+            {
+                issues.push(issue);
+            }
+        }
+    }
+
+    for definitions in profiles {
+        if let Some(ixit) = config.ixit.get(definitions.profile) {
+            let context = format!("for profile {}", definitions.profile);
+            check_pixit(definitions, ixit, &context, &mut issues);
+        }
+    }
+
+    for (key, overrides) in config.tests.iter() {
+        let profile = key.split('/').next().unwrap_or(key);
+        if let Some(definitions) = profiles.iter().find(|d| d.profile == profile) {
+            let context = format!("for tests '{}'", key);
+            check_pics(definitions, overrides.ics.keys(), &context, &mut issues);
+            check_pixit(definitions, &overrides.ixit, &context, &mut issues);
+        }
+    }
+
+    issues.sort_by(|a, b| a.message.cmp(&b.message));
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_valid_value, levenshtein, suggestions, validate, Definitions, Severity};
+    use crate::config::Config;
+    use libpts::{PicsRow, PixitRow};
+
+    fn pics(name: &str) -> PicsRow {
+        PicsRow {
+            name: name.to_owned(),
+            description: String::new(),
+            value: false,
+            mandatory: false,
+        }
+    }
+
+    fn pixit(name: &str, value_type: &str) -> PixitRow {
+        PixitRow {
+            name: name.to_owned(),
+            description: String::new(),
+            value_type: vec![value_type.to_owned()],
+            value: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("TSPC_L2CAP_2_99", "TSPC_L2CAP_2_9"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let names = ["TSPX_psm", "TSPX_time_guard", "TSPX_use_implicit_send"];
+        assert_eq!(
+            suggestions("TSPX_time_gaurd", names.iter().copied()),
+            ["TSPX_time_guard"]
+        );
+        assert!(suggestions("TSPX_unrelated", names.iter().copied()).is_empty());
+    }
+
+    #[test]
+    fn test_is_valid_value() {
+        assert!(is_valid_value("BOOLEAN", "TRUE"));
+        assert!(!is_valid_value("BOOLEAN", "1"));
+        assert!(is_valid_value("INTEGER", "-60000"));
+        assert!(!is_valid_value("INTEGER", "60s"));
+        assert!(is_valid_value("OCTETSTRING", "0001"));
+        assert!(!is_valid_value("OCTETSTRING", "001"));
+        assert!(is_valid_value("IA5STRING", "anything"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "ics": { "TSPC_L2CAP_2_99": true, "TSPC_L2CAP_2_9": true, "TSPC_A2DP_1_1": true },
+                "ixit": {
+                    "default": { "TSPX_time_guard": "180000" },
+                    "L2CAP": { "TSPX_psm": "01" }
+                },
+                "tests": {
+                    "L2CAP/COS/*": { "ixit": { "TSPX_time_guard": "forever" } }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let pics = [pics("TSPC_L2CAP_2_9")];
+        let pixit = [
+            pixit("TSPX_psm", "OCTETSTRING"),
+            pixit("TSPX_time_guard", "INTEGER"),
+        ];
+        let definitions = [Definitions {
+            profile: "L2CAP",
+            pics: pics.iter().collect(),
+            pixit: pixit.iter().collect(),
+        }];
+
+        let issues = validate(&config, &definitions);
+        let messages = issues
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                "error: Invalid value 'forever' for IXIT 'TSPX_time_guard' for tests 'L2CAP/COS/*', expected INTEGER",
+                "warning: Unknown ICS 'TSPC_L2CAP_2_99' for profile L2CAP, did you mean 'TSPC_L2CAP_2_9'?",
+            ]
+        );
+        assert_eq!(issues[0].severity, Severity::Error);
+    }
+}